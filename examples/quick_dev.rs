@@ -20,9 +20,15 @@ async fn main() -> Result<()> {
     .print()
     .await?;
 
+    hc.do_post("/api/workspace/switch", json!({"id": 1000}))
+        .await?
+        .print()
+        .await?;
+
     hc.do_post(
         "/api/rpc",
         json!({
+            "jsonrpc": "2.0",
             "id": 1,
             "method": "create_task",
             "params": {
@@ -39,6 +45,7 @@ async fn main() -> Result<()> {
     hc.do_post(
         "/api/rpc",
         json!({
+            "jsonrpc": "2.0",
             "id": 1,
             "method": "list_tasks",
         }),
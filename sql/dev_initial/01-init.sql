@@ -3,10 +3,174 @@ CREATE TABLE IF NOT EXISTS users (
     username VARCHAR(255) NOT NULL UNIQUE,
     pwd VARCHAR(255),
     pwd_salt UUID NOT NULL DEFAULT gen_random_uuid(),
-    token_salt UUID NOT NULL DEFAULT gen_random_uuid()
+    token_salt UUID NOT NULL DEFAULT gen_random_uuid(),
+    -- Default workspace, when not chosen by the session or request header.
+    workspace_id BIGINT,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS workspaces (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMPTZ NOT NULL
 );
 
+CREATE TYPE workspace_role AS ENUM ('owner', 'member');
+
+CREATE TABLE IF NOT EXISTS workspace_members (
+    workspace_id BIGINT NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role workspace_role NOT NULL,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS workspace_members_user_id_idx ON workspace_members (user_id);
+
+CREATE TABLE IF NOT EXISTS projects (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+    workspace_id BIGINT NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMPTZ NOT NULL,
+    -- Target of the tasks foreign key, so a task project is of its workspace.
+    UNIQUE (id, workspace_id)
+);
+
+CREATE INDEX IF NOT EXISTS projects_workspace_id_idx ON projects (workspace_id);
+
+CREATE TYPE task_status AS ENUM ('todo', 'in_progress', 'done');
+
 CREATE TABLE IF NOT EXISTS tasks (
     id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
-    title VARCHAR(255) NOT NULL
+    workspace_id BIGINT NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    project_id BIGINT,
+    -- Parent task, of the same workspace and owner.
+    parent_id BIGINT,
+    title VARCHAR(255) NOT NULL,
+    description TEXT,
+    status task_status NOT NULL DEFAULT 'todo',
+    due_at TIMESTAMPTZ,
+    -- 0 (none) to 3 (high).
+    priority SMALLINT NOT NULL DEFAULT 0,
+    owner_id BIGINT NOT NULL,
+    deleted_at TIMESTAMPTZ,
+    version BIGINT NOT NULL DEFAULT 0,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMPTZ NOT NULL,
+    -- ProjectBmc refuses to delete a project with tasks, the soft deleted
+    -- ones are detached (`SET NULL (column)` requires PostgreSQL 15+).
+    CONSTRAINT tasks_project_id_fkey FOREIGN KEY (project_id, workspace_id)
+        REFERENCES projects (id, workspace_id) ON DELETE SET NULL (project_id),
+    -- Target of the subtasks foreign key.
+    UNIQUE (id, workspace_id, owner_id),
+    -- TaskBmc moves the children of a deleted task under its parent.
+    -- `SET NULL (column)` requires PostgreSQL 15+.
+    CONSTRAINT tasks_parent_id_fkey FOREIGN KEY (parent_id, workspace_id, owner_id)
+        REFERENCES tasks (id, workspace_id, owner_id) ON DELETE SET NULL (parent_id)
+);
+
+CREATE INDEX IF NOT EXISTS tasks_workspace_id_idx ON tasks (workspace_id);
+CREATE INDEX IF NOT EXISTS tasks_project_id_idx ON tasks (project_id);
+CREATE INDEX IF NOT EXISTS tasks_parent_id_idx ON tasks (parent_id);
+CREATE INDEX IF NOT EXISTS tasks_status_idx ON tasks (status);
+CREATE INDEX IF NOT EXISTS tasks_due_at_idx ON tasks (due_at);
+CREATE INDEX IF NOT EXISTS tasks_owner_id_idx ON tasks (owner_id);
+
+CREATE TABLE IF NOT EXISTS labels (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+    workspace_id BIGINT NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS labels_workspace_id_idx ON labels (workspace_id);
+
+-- Labels of the tasks, of the same workspace (checked by LabelBmc).
+CREATE TABLE IF NOT EXISTS task_labels (
+    task_id BIGINT NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    label_id BIGINT NOT NULL REFERENCES labels (id) ON DELETE CASCADE,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (task_id, label_id)
+);
+
+CREATE INDEX IF NOT EXISTS task_labels_label_id_idx ON task_labels (label_id);
+
+CREATE TABLE IF NOT EXISTS comments (
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+    workspace_id BIGINT NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+    task_id BIGINT NOT NULL REFERENCES tasks (id) ON DELETE CASCADE,
+    -- Comment replied to, of the same task.
+    parent_id BIGINT,
+    author_id BIGINT NOT NULL,
+    content TEXT NOT NULL,
+    edited_at TIMESTAMPTZ,
+    deleted_at TIMESTAMPTZ,
+
+    cid BIGINT NOT NULL,
+    ctime TIMESTAMPTZ NOT NULL,
+    mid BIGINT NOT NULL,
+    mtime TIMESTAMPTZ NOT NULL,
+    -- Target of the replies foreign key.
+    UNIQUE (id, task_id),
+    CONSTRAINT comments_parent_id_fkey FOREIGN KEY (parent_id, task_id)
+        REFERENCES comments (id, task_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS comments_task_id_idx ON comments (task_id);
+CREATE INDEX IF NOT EXISTS comments_parent_id_idx ON comments (parent_id);
+
+-- Row level security, on top of the model layer checks. `app.user_id` and
+-- `app.workspace_id` are set by the ModelManager from the request Ctx,
+-- user `0` for the root ctx, which sees all the workspaces when it has
+-- none. Forced, since app_user owns the table. Without the settings, no row.
+ALTER TABLE tasks ENABLE ROW LEVEL SECURITY;
+ALTER TABLE tasks FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY tasks_owner ON tasks
+    USING (NULLIF(current_setting('app.user_id', true), '')::BIGINT IN (0, owner_id));
+
+CREATE POLICY tasks_workspace ON tasks AS RESTRICTIVE
+    USING (
+        workspace_id = NULLIF(current_setting('app.workspace_id', true), '')::BIGINT
+        OR (
+            NULLIF(current_setting('app.user_id', true), '')::BIGINT = 0
+            AND NULLIF(current_setting('app.workspace_id', true), '') IS NULL
+        )
+    );
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    user_id BIGINT NOT NULL,
+    -- Workspace of the call, NULL without one.
+    workspace_id BIGINT,
+    key VARCHAR(255) NOT NULL,
+    method VARCHAR(255) NOT NULL,
+    params JSONB,
+    -- NULL while the call is in progress.
+    result JSONB,
+    ctime TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE NULLS NOT DISTINCT (user_id, workspace_id, key)
 );
+CREATE INDEX IF NOT EXISTS idempotency_keys_ctime_idx ON idempotency_keys (ctime);
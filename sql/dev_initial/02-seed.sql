@@ -1 +1,13 @@
-INSERT INTO users (username) VALUES ('demo1');
+INSERT INTO users (username, cid, ctime, mid, mtime) VALUES ('demo1', 0, now(), 0, now());
+
+-- Without workspace, to be added as a member.
+INSERT INTO users (username, cid, ctime, mid, mtime) VALUES ('demo2', 0, now(), 0, now());
+
+INSERT INTO workspaces (name, cid, ctime, mid, mtime) VALUES ('demo1', 0, now(), 0, now());
+
+INSERT INTO workspace_members (workspace_id, user_id, role, cid, ctime)
+    SELECT workspaces.id, users.id, 'owner', 0, now() FROM workspaces, users
+    WHERE workspaces.name = 'demo1' AND users.username = 'demo1';
+
+UPDATE users SET workspace_id = (SELECT id FROM workspaces WHERE name = 'demo1')
+    WHERE username = 'demo1';
@@ -39,18 +39,20 @@ pub async fn init_dev_db() -> Result<(), Box<dyn std::error::Error>> {
                 continue;
             }
 
-            pexec(&db, &path).await?;
+            pexec(&db, path).await?;
         }
     }
 
     let mm = ModelManager::new().await?;
     let ctx = Ctx::root_ctx();
 
-    let demo1_user: User = UserBmc::first_by_username(&ctx, &mm, "demo1")
-        .await?
-        .unwrap();
+    for username in ["demo1", "demo2"] {
+        let user: User = UserBmc::first_by_username(&ctx, &mm, username)
+            .await?
+            .unwrap();
 
-    UserBmc::update_pwd(&ctx, &mm, demo1_user.id, DEMO_PWD).await?;
+        UserBmc::update_pwd(&ctx, &mm, user.id, DEMO_PWD).await?;
+    }
 
     Ok(())
 }
@@ -12,6 +12,19 @@ use crate::{
     },
 };
 
+/// Seeded workspace of demo1, which the test ctxs are in.
+pub const DEMO_WORKSPACE_ID: i64 = 1000;
+
+/// Ctx of `user_id` (root for 0) in the `DEMO_WORKSPACE_ID`.
+pub fn ctx_test(user_id: i64) -> Ctx {
+    let ctx = match user_id {
+        0 => Ctx::root_ctx(),
+        user_id => Ctx::new(user_id).unwrap(),
+    };
+
+    ctx.with_workspace_id(Some(DEMO_WORKSPACE_ID))
+}
+
 pub async fn init_dev() {
     static INIT: OnceCell<()> = OnceCell::const_new();
 
@@ -41,6 +54,7 @@ pub async fn seed_test(ctx: &Ctx, mm: &ModelManager, titles: &[&str]) -> model::
     for title in titles {
         let task_c = TaskForCreate {
             title: title.to_string(),
+            ..Default::default()
         };
 
         let id = TaskBmc::create(ctx, mm, task_c).await?;
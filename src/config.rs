@@ -16,6 +16,9 @@ pub struct Config {
     pub PWD_KEY: Vec<u8>,
     pub TOKEN_KEY: Vec<u8>,
     pub TOKEN_DURATION_SEC: f64,
+    pub RPC_BATCH_MAX_SIZE: usize,
+    pub RPC_BATCH_CONCURRENT: bool,
+    pub IDEMPOTENCY_TTL_SEC: f64,
 }
 
 impl Config {
@@ -26,6 +29,9 @@ impl Config {
             PWD_KEY: get_env_b64u_as_u8s("SERVICE_PWD_KEY")?,
             TOKEN_KEY: get_env_b64u_as_u8s("SERVICE_PWD_KEY")?,
             TOKEN_DURATION_SEC: gen_env_parse::<f64>("SERVICE_TOKEN_DURATION_SEC")?,
+            RPC_BATCH_MAX_SIZE: gen_env_parse::<usize>("SERVICE_RPC_BATCH_MAX_SIZE")?,
+            RPC_BATCH_CONCURRENT: gen_env_parse::<bool>("SERVICE_RPC_BATCH_CONCURRENT")?,
+            IDEMPOTENCY_TTL_SEC: gen_env_parse::<f64>("SERVICE_IDEMPOTENCY_TTL_SEC")?,
         })
     }
 }
@@ -8,7 +8,7 @@ use crate::crypt::{
 pub fn encrypt_pwd(enc_content: &EncryptContent) -> Result<String> {
     let key = &config().PWD_KEY;
 
-    let encrypted = encrypt_into_b64u(&key, enc_content)?;
+    let encrypted = encrypt_into_b64u(key, enc_content)?;
 
     Ok(format!("#01#{encrypted}"))
 }
@@ -16,8 +16,6 @@ pub fn encrypt_pwd(enc_content: &EncryptContent) -> Result<String> {
 pub fn validate_pwd(enc_content: &EncryptContent, encrypted: &str) -> Result<()> {
     let expected = encrypt_pwd(enc_content)?;
 
-    let encrypted = encrypted.trim_start_matches("#01#");
-
     if encrypted != expected {
         return Err(Error::PasswordNotMatch);
     }
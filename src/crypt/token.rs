@@ -16,7 +16,7 @@ impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Token(ident: {}, exp: {}, sign_b64u: {})",
+            "{}.{}.{}",
             b64u_encode(&self.ident),
             b64u_encode(&self.exp),
             self.sign_b64u
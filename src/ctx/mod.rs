@@ -5,6 +5,8 @@ use error::{Error, Result};
 #[derive(Clone, Debug)]
 pub struct Ctx {
     user_id: i64,
+    /// Current workspace, which the workspaced entities are scoped to.
+    workspace_id: Option<i64>,
 }
 
 impl Ctx {
@@ -12,14 +14,34 @@ impl Ctx {
         if user_id == 0 {
             return Err(Error::CtxCannotNewRootCtx);
         }
-        Ok(Self { user_id })
+        Ok(Self {
+            user_id,
+            workspace_id: None,
+        })
     }
 
     pub fn root_ctx() -> Self {
-        Self { user_id: 0 }
+        Self {
+            user_id: 0,
+            workspace_id: None,
+        }
+    }
+
+    pub fn with_workspace_id(mut self, workspace_id: Option<i64>) -> Self {
+        self.workspace_id = workspace_id;
+        self
     }
 
     pub fn user_id(&self) -> i64 {
         self.user_id
     }
+
+    pub fn workspace_id(&self) -> Option<i64> {
+        self.workspace_id
+    }
+
+    /// Whether this is the root ctx, which bypasses the access checks.
+    pub fn is_root(&self) -> bool {
+        self.user_id == 0
+    }
 }
@@ -1,8 +1,4 @@
 use crate::model;
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
 use serde::Serialize;
 use serde_with::serde_as;
 
@@ -15,10 +11,6 @@ pub enum Error {
     ConfigMissingEnv(&'static str),
     ConfigWrongFormat(&'static str),
     Model(model::Error),
-
-    LoginFailUsernameNotFound,
-    LoginFailUserHasNoPwd { user_id: i64 },
-    LoginFailPwdNotMatching { user_id: i64 },
 }
 
 impl core::fmt::Display for Error {
@@ -34,53 +26,3 @@ impl From<model::Error> for Error {
         Self::Model(val)
     }
 }
-
-impl IntoResponse for Error {
-    fn into_response(self) -> Response {
-        let mut response = StatusCode::INTERNAL_SERVER_ERROR.into_response();
-
-        response.extensions_mut().insert(self);
-
-        response
-    }
-}
-
-impl Error {
-    pub fn client_status_and_error(&self) -> (StatusCode, ClientError) {
-        use Error::*;
-
-        match self {
-            // -- Login
-            LoginFailUsernameNotFound
-            | LoginFailUserHasNoPwd { .. }
-            | LoginFailPwdNotMatching { .. } => (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL),
-
-            // -- Model
-            Model(model::Error::EntityNotFound { entity, id }) => (
-                StatusCode::BAD_REQUEST,
-                ClientError::ENTITY_NOT_FOUND { entity, id: *id },
-            ),
-
-            // -- Fallback.
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                ClientError::SERVICE_ERROR,
-            ),
-        }
-    }
-}
-
-#[derive(Debug, Serialize, strum_macros::AsRefStr)]
-#[serde(tag = "message", content = "detail")]
-#[allow(non_camel_case_types)]
-pub enum ClientError {
-    LOGIN_FAIL,
-    NO_AUTH,
-    ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
-
-    RPC_REQUEST_INVALID(String),
-    RPC_REQUEST_METHOD_UNKNOWN(String),
-    RPC_PARAMS_INVALID(String),
-
-    SERVICE_ERROR,
-}
@@ -1,6 +1,7 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::{ctx::Ctx, error::ClientError, Error, Result};
+use crate::web::{self, rpc::RpcInfo, ClientError};
+use crate::{ctx::Ctx, Result};
 use axum::http::Uri;
 use serde::Serialize;
 use serde_json::{json, Value};
@@ -11,8 +12,9 @@ pub async fn log_request(
     uuid: String,
     req_method: String,
     uri: Uri,
+    rpc_info: Option<&RpcInfo>,
     ctx: Option<Ctx>,
-    service_error: Option<&Error>,
+    web_error: Option<&web::Error>,
     client_error: Option<ClientError>,
 ) -> Result<()> {
     let timestamp = SystemTime::now()
@@ -20,8 +22,8 @@ pub async fn log_request(
         .unwrap()
         .as_millis();
 
-    let error_type = service_error.map(|se| se.as_ref().to_string());
-    let error_data = serde_json::to_value(service_error)
+    let error_type = web_error.map(|we| we.as_ref().to_string());
+    let error_data = serde_json::to_value(web_error)
         .ok()
         .and_then(|mut v| v.get_mut("data").map(|v| v.take()));
 
@@ -32,6 +34,13 @@ pub async fn log_request(
         req_path: uri.to_string(),
         req_method: req_method.to_string(),
 
+        rpc_id: rpc_info.and_then(|rpc| rpc.id.as_ref()).map(|id| {
+            id.as_str()
+                .map(String::from)
+                .unwrap_or_else(|| id.to_string())
+        }),
+        rpc_method: rpc_info.and_then(|rpc| rpc.method.clone()),
+
         user_id: ctx.map(|ctx| ctx.user_id()),
 
         client_error_type: client_error.map(|ce| ce.as_ref().to_string()),
@@ -44,6 +53,34 @@ pub async fn log_request(
     Ok(())
 }
 
+/// Logs one request line per rpc call, for JSON-RPC payloads which
+/// carry their errors in the response body.
+pub async fn log_rpc_calls(
+    uuid: String,
+    req_method: String,
+    uri: Uri,
+    ctx: Option<Ctx>,
+    rpc_infos: &[RpcInfo],
+) -> Result<()> {
+    for rpc_info in rpc_infos {
+        let web_error = rpc_info.error.as_deref();
+        let client_error = web_error.map(|we| we.client_status_and_error().1);
+
+        log_request(
+            uuid.clone(),
+            req_method.clone(),
+            uri.clone(),
+            Some(rpc_info),
+            ctx.clone(),
+            web_error,
+            client_error,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
 #[skip_serializing_none]
 #[derive(Serialize)]
 struct RequestLogLine {
@@ -55,6 +92,9 @@ struct RequestLogLine {
     req_path: String,
     req_method: String,
 
+    rpc_id: Option<String>,
+    rpc_method: Option<String>,
+
     client_error_type: Option<String>,
     error_type: Option<String>,
     error_data: Option<Value>,
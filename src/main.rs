@@ -13,6 +13,7 @@ pub use self::error::{Error, Result};
 pub use config::config;
 
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use axum::{
     extract::{Path, Query},
@@ -30,7 +31,7 @@ use tower_cookies::CookieManagerLayer;
 use tracing::info;
 use web::{
     mw_auth::{mw_ctx_require, CtxW},
-    rpc,
+    rpc::{self, RpcInfo},
 };
 
 #[tokio::main]
@@ -48,12 +49,15 @@ async fn main() -> Result<()> {
 
     let mm = ModelManager::new().await?;
 
-    let routes_rpc = rpc::routes(mm.clone()).route_layer(middleware::from_fn(mw_ctx_require));
+    let routes_api = rpc::routes(mm.clone())
+        .merge(web::routes_events::routes(mm.clone()))
+        .merge(web::routes_workspace::routes(mm.clone()))
+        .route_layer(middleware::from_fn(mw_ctx_require));
 
     let routes_all = Router::new()
         .merge(routes_hello())
         .merge(web::routes_login::routes(mm.clone()))
-        .nest("/api", routes_rpc)
+        .nest("/api", routes_api)
         .layer(middleware::map_response(main_response_mapper))
         .layer(middleware::from_fn_with_state(
             mm.clone(),
@@ -80,9 +84,27 @@ async fn main_response_mapper(
     info!("->> {:<12} - main_response_mapper", "RES_MAPPER");
 
     let uuid = uuid::Uuid::new_v4();
+    let ctx = ctx.map(|ctx| ctx.0);
 
-    let service_error = res.extensions().get::<Error>();
-    let client_status_error = service_error.map(|e| e.client_status_and_error());
+    // -- Rpc responses already carry their JSON-RPC error objects,
+    //    so only log them, one line per call.
+    if let Some(rpc_infos) = res.extensions().get::<Vec<RpcInfo>>() {
+        let _ = log::log_rpc_calls(
+            uuid.to_string(),
+            req_method.to_string(),
+            uri,
+            ctx,
+            rpc_infos,
+        )
+        .await;
+
+        println!();
+
+        return res;
+    }
+
+    let web_error = res.extensions().get::<Arc<web::Error>>().map(Arc::as_ref);
+    let client_status_error = web_error.map(|e| e.client_status_and_error());
 
     let error_response = client_status_error.as_ref().map(|(status, client_error)| {
         let client_error_body = json!({
@@ -98,8 +120,9 @@ async fn main_response_mapper(
         uuid.to_string(),
         req_method.to_string(),
         uri,
-        ctx.map(|ctx| ctx.0),
-        service_error,
+        None,
+        ctx,
+        web_error,
         client_error,
     )
     .await;
@@ -1,117 +1,1543 @@
+use std::collections::HashSet;
+
 use crate::ctx::Ctx;
+use crate::model::cursor::{decode_cursor, encode_cursor, push_after_sql};
+use crate::model::event::{EventScope, ModelEventKind};
+use crate::model::filter::Filter;
+use crate::model::list_options::{ListOptions, Page};
+use crate::model::validation::{self, FieldError, Validate, Validator};
 use crate::model::ModelManager;
 use crate::model::{Error, Result};
-use sqlb::{HasFields, SqlBuilder};
-use sqlx::postgres::PgRow;
-use sqlx::FromRow;
+use crate::util::now_utc;
+use serde::Serialize;
+use sqlb::{Field, Fields, HasFields, Raw, Whereable};
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::Query;
+use sqlx::{FromRow, Postgres, QueryBuilder, Row};
+use time::OffsetDateTime;
 
 pub trait DbBmc {
     const TABLE: &'static str;
+
+    /// When true, `delete` only marks the rows as deleted (`deleted_at`
+    /// column), and they are excluded by `get`, `list` and `update` until
+    /// `restore`d. `purge` deletes them for good.
+    const SOFT_DELETE: bool = false;
+
+    /// When true, the `version` column is incremented by each `update`,
+    /// which can be conditioned on the expected version.
+    const VERSIONED: bool = false;
+
+    /// When true, the `owner_id` column is set to the creating ctx user, and
+    /// the entities are only accessible to their owner (and the root ctx).
+    const OWNED: bool = false;
+
+    /// When true, the `workspace_id` column is set to the ctx workspace, and
+    /// the entities are only accessible in their workspace. A ctx without
+    /// workspace fails with `WorkspaceRequired`, except the root ctx which
+    /// then accesses all of them (but still cannot create).
+    const WORKSPACED: bool = false;
+
+    /// Foreign key columns, whose constraint is named `{TABLE}_{column}_fkey`.
+    /// Creating or updating an entity with a reference not found (or soft
+    /// deleted) fails with a `ValidationFailed` on the column (per item for
+    /// the bulk functions).
+    const REFERENCES: &'static [Reference] = &[];
+
+    /// SQL types of the columns whose values are not comparable with a json
+    /// scalar (like enums and timestamps, with a text), by column name. Their
+    /// filter and cursor values are cast to it. Defaults to the audit
+    /// timestamps, to keep when overridden.
+    const COLUMN_TYPES: &'static [(&'static str, &'static str)] =
+        &[("ctime", "timestamptz"), ("mtime", "timestamptz")];
+
+    /// Filter fields of join tables, as `(field, table, entity id column)`.
+    /// A filter on such a field matches the entities with at least one row
+    /// of the table matching it.
+    const RELATED_FIELDS: &'static [(&'static str, &'static str, &'static str)] = &[];
+
+    /// When true, the `parent_id` column links the entities into trees
+    /// (see `get_subtree` and `move_to_parent`). Deleting an entity moves its
+    /// children under its parent.
+    const TREE: bool = false;
+
+    /// When true, the entities are the workspaces themselves, their events
+    /// only visible in them (like the ones of a `WORKSPACED` entity).
+    const IS_WORKSPACE: bool = false;
+
+    /// When false, the functions of this module publish no event for the
+    /// entities, their Bmc publishing them with their own scope.
+    const PUBLISH_EVENTS: bool = true;
+}
+
+/// Foreign key column of a `REFERENCES` entity.
+pub struct Reference {
+    pub column: &'static str,
+    /// Other columns of the foreign key, of the same name in both tables
+    /// (like `workspace_id`).
+    pub with: &'static [&'static str],
+    /// Table and `scope_sql` of the referenced entities, see `Reference::to`.
+    target: fn(&Ctx) -> (&'static str, String),
+}
+
+impl Reference {
+    /// `column` referencing the entities of `MC` the ctx may access (soft
+    /// deleted ones excluded).
+    pub const fn to<MC: DbBmc>(column: &'static str, with: &'static [&'static str]) -> Self {
+        Self {
+            column,
+            with,
+            target: reference_target::<MC>,
+        }
+    }
+}
+
+fn reference_target<MC: DbBmc>(ctx: &Ctx) -> (&'static str, String) {
+    (MC::TABLE, scope_sql::<MC>(ctx, false))
 }
 
-pub async fn get<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
+/// sqlb where operator matching a `None` value, since sqlb binds every
+/// where value (`= NULL` never matches).
+const OP_IS: &str = "IS NOT DISTINCT FROM";
+const OP_IS_NOT: &str = "IS DISTINCT FROM";
+
+const NO_TIME: Option<OffsetDateTime> = None;
+
+/// Max number of entities of one `create_many`, `update_many` or
+/// `delete_many`.
+pub const BULK_MAX: usize = 1000;
+
+type PgQuery<'q> = Query<'q, Postgres, PgArguments>;
+
+pub async fn get<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
 where
     MC: DbBmc,
     E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
     E: HasFields,
 {
-    let db = mm.db();
-
-    let entity: E = sqlb::select()
-        .table(MC::TABLE)
-        .columns(E::field_names())
-        .and_where("id", "=", id)
-        .fetch_optional(db)
-        .await?
-        .ok_or(Error::EntityNotFound {
-            entity: MC::TABLE,
-            id,
-        })?;
+    get_entity::<MC, E>(ctx, mm, id, false).await
+}
 
-    Ok(entity)
+/// Like `get`, also returning a soft deleted entity.
+pub async fn get_include_deleted<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
+where
+    MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+    E: HasFields,
+{
+    get_entity::<MC, E>(ctx, mm, id, true).await
 }
 
-pub async fn list<MC, E>(_ctx: &Ctx, mm: &ModelManager) -> Result<Vec<E>>
+/// Gets the entities of `ids` found, in no particular order.
+pub async fn get_many<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    ids: &[i64],
+    include_deleted: bool,
+) -> Result<Vec<E>>
 where
     MC: DbBmc,
     E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
     E: HasFields,
 {
-    let db = mm.db();
+    check_workspace::<MC>(ctx)?;
 
-    let entities: Vec<E> = sqlb::select()
-        .table(MC::TABLE)
-        .columns(E::field_names())
-        .order_by("id")
-        .fetch_all(db)
-        .await?;
+    let mut db = mm.db(ctx).await?;
+
+    let sql = format!(
+        "SELECT {} FROM \"{}\" WHERE \"id\" = ANY($1){}",
+        columns_sql(E::field_names()),
+        MC::TABLE,
+        scope_sql::<MC>(ctx, include_deleted)
+    );
+    let entities = sqlx::query_as(&sql).bind(ids).fetch_all(&mut *db).await?;
 
     Ok(entities)
 }
 
-pub async fn create<MC, E>(_ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
+async fn get_entity<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+    include_deleted: bool,
+) -> Result<E>
 where
     MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
     E: HasFields,
 {
-    let db = mm.db();
+    check_workspace::<MC>(ctx)?;
+
+    let mut db = mm.db(ctx).await?;
+
+    let entity: Option<E> = scoped::<MC, _>(
+        ctx,
+        sqlb::select()
+            .table(MC::TABLE)
+            .columns(E::field_names())
+            .and_where("id", "=", id),
+        include_deleted,
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+    drop(db);
+
+    match entity {
+        Some(entity) => Ok(entity),
+        None => Err(not_found_error::<MC>(ctx, mm, id, include_deleted).await?),
+    }
+}
+
+/// Lists the entities matching all the `filters`, paged and ordered by
+/// `list_options`.
+///
+/// The page after a cursor is selected by the sort key values (keyset
+/// pagination), so it stays consistent while entities are inserted.
+pub async fn list<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    filters: Vec<Filter>,
+    list_options: ListOptions,
+) -> Result<Page<E>>
+where
+    MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+    E: HasFields + Serialize,
+{
+    check_workspace::<MC>(ctx)?;
 
-    let fields = data.not_none_fields();
+    let mut v = Validator::default();
+    for filter in &filters {
+        filter.validate(&mut v, &filter_field_names::<MC, E>());
+    }
+    list_options.validate(&mut v, E::field_names());
+    v.finish().map_err(validation_failed::<MC>)?;
+
+    let mut db = mm.db(ctx).await?;
+
+    let filter = Filter::And { and: filters };
+    let scope = scope_sql::<MC>(ctx, list_options.include_deleted.unwrap_or(false));
+    let limit = list_options.limit();
+    let offset = list_options.offset();
+    let sort_key = list_options.sort_key();
+
+    let after_values = match &list_options.cursor {
+        Some(cursor) => Some(decode_cursor(MC::TABLE, &sort_key, cursor)?.ok_or_else(|| {
+            validation_failed::<MC>(vec![FieldError {
+                field: "cursor",
+                rule: "cursor_valid",
+                message: "cursor invalid, or not for this order".to_string(),
+            }])
+        })?),
+        None => None,
+    };
+
+    let columns = columns_sql(E::field_names());
+
+    let mut qb =
+        QueryBuilder::<Postgres>::new(format!("SELECT {columns} FROM \"{}\" WHERE ", MC::TABLE));
+    filter.push_sql::<MC>(&mut qb);
+    qb.push(&scope);
+    if let Some(after_values) = &after_values {
+        qb.push(" AND ");
+        push_after_sql(&mut qb, &sort_key, after_values, MC::COLUMN_TYPES);
+    }
+    // One more than the limit, to know if there is a next page.
+    qb.push(format!(" ORDER BY {}", list_options.order_by_sql()))
+        .push(" LIMIT ")
+        .push_bind(limit as i64 + 1)
+        .push(" OFFSET ")
+        .push_bind(offset as i64);
+    let mut items: Vec<E> = qb
+        .build_query_as()
+        .fetch_all(&mut *db)
+        .await
+        .map_err(filter_sqlx_error::<MC>)?;
+
+    let has_next = items.len() > limit as usize;
+    items.truncate(limit as usize);
+
+    let next_cursor = match items.last().filter(|_| has_next) {
+        Some(last) => Some(encode_cursor(
+            MC::TABLE,
+            &sort_key,
+            &serde_json::to_value(last).map_err(|ex| Error::SerdeJson(ex.to_string()))?,
+        )?),
+        None => None,
+    };
+
+    let mut qb =
+        QueryBuilder::<Postgres>::new(format!("SELECT COUNT(*) FROM \"{}\" WHERE ", MC::TABLE));
+    filter.push_sql::<MC>(&mut qb);
+    qb.push(&scope);
+    let (total,): (i64,) = qb
+        .build_query_as()
+        .fetch_one(&mut *db)
+        .await
+        .map_err(filter_sqlx_error::<MC>)?;
+
+    Ok(Page {
+        items,
+        total,
+        limit,
+        offset,
+        next_cursor,
+    })
+}
+
+pub async fn create<MC, E>(ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
+where
+    MC: DbBmc,
+    E: HasFields + Validate,
+{
+    validation::validate(&data).map_err(validation_failed::<MC>)?;
+
+    let mut fields = data.not_none_fields();
+    add_scope_for_create::<MC>(&mut fields, ctx)?;
+    add_timestamps_for_create(&mut fields, ctx.user_id());
+
+    // Soft deleted references, accepted by the foreign keys.
+    let rows = std::slice::from_ref(&fields);
+    if let Some((_, column)) = invalid_references::<MC>(ctx, mm, rows).await?.first() {
+        return Err(reference_error::<MC>(column));
+    }
+
+    let mut db = mm.db(ctx).await?;
 
     let (id,) = sqlb::insert()
         .table(MC::TABLE)
         .data(fields)
         .returning(&["id"])
-        .fetch_one::<_, (i64,)>(db)
-        .await?;
+        .fetch_one::<_, (i64,)>(&mut *db)
+        .await
+        .map_err(reference_sqlx_error::<MC>)?;
+
+    publish_event::<MC>(mm, id, ctx_scope::<MC>(ctx, id), ModelEventKind::Created);
 
     Ok(id)
 }
 
-pub async fn update<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64, data: E) -> Result<()>
+/// Ids of the entities matching all the `filters`, locked for update until
+/// the end of the transaction.
+///
+/// Fails with a `ValidationFailed` when more than `BULK_MAX` entities match.
+pub async fn list_ids<MC, E>(ctx: &Ctx, mm: &ModelManager, filters: Vec<Filter>) -> Result<Vec<i64>>
 where
     MC: DbBmc,
     E: HasFields,
 {
-    let db = mm.db();
+    check_workspace::<MC>(ctx)?;
 
-    let fields = data.not_none_fields();
+    let mut v = Validator::default();
+    for filter in &filters {
+        filter.validate(&mut v, &filter_field_names::<MC, E>());
+    }
+    v.finish().map_err(validation_failed::<MC>)?;
 
-    let count = sqlb::update()
-        .table(MC::TABLE)
-        .and_where("id", "=", id)
-        .data(fields)
-        .exec(db)
-        .await?;
+    let mut db = mm.db(ctx).await?;
+
+    let filter = Filter::And { and: filters };
+    let mut qb =
+        QueryBuilder::<Postgres>::new(format!("SELECT \"id\" FROM \"{}\" WHERE ", MC::TABLE));
+    filter.push_sql::<MC>(&mut qb);
+    qb.push(scope_sql::<MC>(ctx, false))
+        .push(" ORDER BY \"id\" LIMIT ")
+        .push_bind(BULK_MAX as i64 + 1)
+        .push(" FOR UPDATE");
+    let ids: Vec<(i64,)> = qb
+        .build_query_as()
+        .fetch_all(&mut *db)
+        .await
+        .map_err(filter_sqlx_error::<MC>)?;
+
+    check_bulk_size::<MC>("filters", ids.len())?;
+
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+/// Creates the valid `data` items with one multi-row insert.
+///
+/// Returns one result per item, in order, the new entity id or the
+/// `ValidationFailed` error of the item (the invalid items are skipped).
+pub async fn create_many<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    data: Vec<E>,
+) -> Result<Vec<Result<i64>>>
+where
+    MC: DbBmc,
+    E: HasFields + Validate,
+{
+    check_bulk_size::<MC>("data", data.len())?;
+
+    let mut results: Vec<Option<Result<i64>>> = Vec::with_capacity(data.len());
+    let mut rows: Vec<Vec<Field>> = Vec::new();
+    for item in data {
+        match validation::validate(&item) {
+            Ok(()) => {
+                let mut fields = item.not_none_fields();
+                add_scope_for_create::<MC>(&mut fields, ctx)?;
+                add_timestamps_for_create(&mut fields, ctx.user_id());
+                rows.push(fields);
+                results.push(None);
+            }
+            Err(errors) => results.push(Some(Err(validation_failed::<MC>(errors)))),
+        }
+    }
+
+    // Checked before the insert, which would fail as a whole.
+    let invalid_rows = invalid_references::<MC>(ctx, mm, &rows).await?;
+    if !invalid_rows.is_empty() {
+        // Results of the rows, in the same order.
+        let mut row_results = results.iter_mut().filter(|result| result.is_none());
+        let mut row_idx = 0;
+        rows.retain(|_| {
+            let result = row_results.next();
+            let invalid = invalid_rows.iter().find(|(idx, _)| *idx == row_idx);
+            row_idx += 1;
+
+            match (result, invalid) {
+                (Some(result), Some((_, column))) => {
+                    *result = Some(Err(reference_error::<MC>(column)));
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+
+    let mut ids = Vec::new();
+    if !rows.is_empty() {
+        // Union of the row columns, the ones a row does not have get their
+        // column default.
+        let mut columns: Vec<&str> = Vec::new();
+        for field in rows.iter().flatten() {
+            if !columns.contains(&field.name.as_str()) {
+                columns.push(&field.name);
+            }
+        }
+
+        let mut binding_idx = 1;
+        let values = rows
+            .iter()
+            .map(|row| {
+                let row_values = columns
+                    .iter()
+                    .map(
+                        |column| match row.iter().find(|field| field.name == *column) {
+                            Some(field) => value_sql(field, &mut binding_idx),
+                            None => "DEFAULT".to_string(),
+                        },
+                    )
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({row_values})")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let sql = format!(
+            "INSERT INTO \"{}\" ({}) VALUES {values} RETURNING \"id\"",
+            MC::TABLE,
+            columns_sql(&columns)
+        );
+
+        let mut query = sqlx::query(&sql);
+        for row in &rows {
+            query = bind_fields(
+                query,
+                columns
+                    .iter()
+                    .filter_map(|column| row.iter().find(|field| field.name == *column)),
+            );
+        }
+
+        let mut db = mm.db(ctx).await?;
+        // Multi-row insert returns the rows in the values order.
+        let rows = query
+            .fetch_all(&mut *db)
+            .await
+            .map_err(reference_sqlx_error::<MC>)?;
+        for row in rows {
+            ids.push(row.try_get::<i64, _>("id")?);
+        }
+    }
+
+    let mut ids = ids.into_iter();
+    let mut created = Vec::with_capacity(results.len());
+    for result in results {
+        created.push(match result {
+            Some(result) => result,
+            None => {
+                let id = ids.next().ok_or(sqlx::Error::RowNotFound)?;
+                publish_event::<MC>(mm, id, ctx_scope::<MC>(ctx, id), ModelEventKind::Created);
+                Ok(id)
+            }
+        });
+    }
+
+    Ok(created)
+}
+
+/// Updates the entity with the not none `data` fields.
+///
+/// For a `VERSIONED` entity, when `version` is given, the update only
+/// happens if it is still the entity version, otherwise fails with a
+/// `Conflict` (see `with_conflict_current`).
+pub async fn update<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+    data: E,
+    version: Option<i64>,
+) -> Result<()>
+where
+    MC: DbBmc,
+    E: HasFields + Validate,
+{
+    check_workspace::<MC>(ctx)?;
+
+    validation::validate(&data).map_err(validation_failed::<MC>)?;
+
+    let mut fields = data.not_none_fields();
+    add_timestamps_for_update(&mut fields, ctx.user_id());
+    if MC::VERSIONED {
+        fields.push(("version", Raw("version + 1")).into());
+    }
+
+    // Soft deleted references, accepted by the foreign keys.
+    if let Some((_, column)) = invalid_reference_ids::<MC>(ctx, mm, &[id], &fields)
+        .await?
+        .first()
+    {
+        return Err(reference_error::<MC>(column));
+    }
+
+    let mut db = mm.db(ctx).await?;
+
+    let version = version.filter(|_| MC::VERSIONED);
+
+    let mut sb = scoped::<MC, _>(
+        ctx,
+        sqlb::update()
+            .table(MC::TABLE)
+            .and_where("id", "=", id)
+            .data(fields),
+        false,
+    );
+    if let Some(version) = version {
+        sb = sb.and_where("version", "=", version);
+    }
+    let count = sb
+        .exec(&mut *db)
+        .await
+        .map_err(reference_sqlx_error::<MC>)?;
 
     if count == 0 {
-        return Err(Error::EntityNotFound {
-            entity: MC::TABLE,
-            id,
+        let exists = version.is_some()
+            && scoped::<MC, _>(
+                ctx,
+                sqlb::select()
+                    .table(MC::TABLE)
+                    .columns(&["id"])
+                    .and_where("id", "=", id),
+                false,
+            )
+            .fetch_optional::<_, (i64,)>(&mut *db)
+            .await?
+            .is_some();
+        drop(db);
+
+        return Err(if exists {
+            Error::Conflict {
+                entity: MC::TABLE,
+                id,
+                current: None,
+            }
+        } else {
+            not_found_error::<MC>(ctx, mm, id, false).await?
         });
     }
+    drop(db);
+
+    let scope = event_scope::<MC>(ctx, mm, id).await?;
+    publish_event::<MC>(mm, id, scope, ModelEventKind::Updated);
 
     Ok(())
 }
 
-pub async fn delete<MC>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+/// Updates the entities of `ids` with the not none `data` fields, with one
+/// multi-row update (without version check).
+///
+/// Returns one result per id, in order, the id or the `EntityNotFound`
+/// (or `AccessDenied`) error of the entity.
+pub async fn update_many<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    ids: &[i64],
+    data: E,
+) -> Result<Vec<Result<i64>>>
 where
     MC: DbBmc,
+    E: HasFields + Validate,
 {
-    let db = mm.db();
+    check_workspace::<MC>(ctx)?;
 
-    let count = sqlb::delete()
-        .table(MC::TABLE)
-        .and_where("id", "=", id)
-        .exec(db)
+    check_bulk_size::<MC>("ids", ids.len())?;
+    validation::validate(&data).map_err(validation_failed::<MC>)?;
+
+    let mut fields = data.not_none_fields();
+    add_timestamps_for_update(&mut fields, ctx.user_id());
+    if MC::VERSIONED {
+        fields.push(("version", Raw("version + 1")).into());
+    }
+
+    // Checked before the update, which would fail as a whole.
+    let invalid_ids = invalid_reference_ids::<MC>(ctx, mm, ids, &fields).await?;
+    let valid_ids: Vec<i64> = ids
+        .iter()
+        .copied()
+        .filter(|id| !invalid_ids.iter().any(|(invalid_id, _)| invalid_id == id))
+        .collect();
+
+    let updated = update_ids::<MC>(ctx, mm, &valid_ids, &fields).await?;
+
+    for (id, scope) in &updated {
+        publish_event::<MC>(mm, *id, *scope, ModelEventKind::Updated);
+    }
+
+    let mut results = bulk_results::<MC>(ctx, mm, ids, &updated).await?;
+    for (id, result) in ids.iter().zip(results.iter_mut()) {
+        if let Some((_, column)) = invalid_ids.iter().find(|(invalid_id, _)| invalid_id == id) {
+            *result = Err(reference_error::<MC>(column));
+        }
+    }
+
+    Ok(results)
+}
+
+/// Adds the `current` entity to the `Conflict` error of an `update`, so
+/// the caller can merge its changes.
+pub async fn with_conflict_current<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    res: Result<()>,
+) -> Result<()>
+where
+    MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+    E: HasFields + Serialize,
+{
+    match res {
+        Err(Error::Conflict {
+            entity,
+            id,
+            current: None,
+        }) => {
+            let current: E = get::<MC, E>(ctx, mm, id).await?;
+            let current =
+                serde_json::to_value(current).map_err(|ex| Error::SerdeJson(ex.to_string()))?;
+
+            Err(Error::Conflict {
+                entity,
+                id,
+                current: Some(current),
+            })
+        }
+        res => res,
+    }
+}
+
+/// Deletes the entity, or marks it as deleted for a `SOFT_DELETE` entity.
+pub async fn delete<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+where
+    MC: DbBmc,
+{
+    check_workspace::<MC>(ctx)?;
+
+    if !MC::SOFT_DELETE {
+        return purge::<MC>(ctx, mm, id).await;
+    }
+
+    // With the move of its children, for a `TREE` entity.
+    mm.transaction(|mm| async move {
+        let mut db = mm.db(ctx).await?;
+
+        let mut fields = vec![("deleted_at", now_utc()).into()];
+        add_timestamps_for_update(&mut fields, ctx.user_id());
+
+        let count = scoped::<MC, _>(
+            ctx,
+            sqlb::update()
+                .table(MC::TABLE)
+                .and_where("id", "=", id)
+                .data(fields),
+            false,
+        )
+        .exec(&mut *db)
         .await?;
+        drop(db);
+
+        if count == 0 {
+            return Err(not_found_error::<MC>(ctx, &mm, id, false).await?);
+        }
+
+        move_children::<MC>(ctx, &mm, id).await?;
+
+        let scope = event_scope::<MC>(ctx, &mm, id).await?;
+        publish_event::<MC>(&mm, id, scope, ModelEventKind::Deleted);
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes the entities of `ids` (or marks them as deleted, see `delete`),
+/// with one multi-row statement.
+///
+/// Returns one result per id, in order, like `update_many`.
+pub async fn delete_many<MC>(ctx: &Ctx, mm: &ModelManager, ids: &[i64]) -> Result<Vec<Result<i64>>>
+where
+    MC: DbBmc,
+{
+    check_workspace::<MC>(ctx)?;
+
+    check_bulk_size::<MC>("ids", ids.len())?;
+
+    // With the move of their children, for a `TREE` entity.
+    mm.transaction(|mm| async move {
+        if MC::TREE && !MC::SOFT_DELETE {
+            // Before the rows are gone, their parents being read.
+            for id in ids {
+                move_children::<MC>(ctx, &mm, *id).await?;
+            }
+        }
+
+        let deleted = if MC::SOFT_DELETE {
+            let mut fields = vec![("deleted_at", now_utc()).into()];
+            add_timestamps_for_update(&mut fields, ctx.user_id());
+
+            update_ids::<MC>(ctx, &mm, ids, &fields).await?
+        } else {
+            let mut db = mm.db(ctx).await?;
+
+            let sql = format!(
+                "DELETE FROM \"{}\" WHERE \"id\" = ANY($1){} RETURNING \"id\", {}",
+                MC::TABLE,
+                scope_sql::<MC>(ctx, false),
+                scope_columns_sql::<MC>()
+            );
+            let rows = sqlx::query(&sql).bind(ids).fetch_all(&mut *db).await?;
+            rows.iter().map(event_row).collect::<Result<_>>()?
+        };
+
+        if MC::SOFT_DELETE {
+            // One by one, in order, so a child of a chain of deleted
+            // entities ends under the closest one not deleted.
+            for (id, _) in &deleted {
+                move_children::<MC>(ctx, &mm, *id).await?;
+            }
+        }
+
+        for (id, scope) in &deleted {
+            publish_event::<MC>(&mm, *id, *scope, ModelEventKind::Deleted);
+        }
+
+        bulk_results::<MC>(ctx, &mm, ids, &deleted).await
+    })
+    .await
+}
+
+/// Restores a soft deleted entity.
+pub async fn restore<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+where
+    MC: DbBmc,
+{
+    check_workspace::<MC>(ctx)?;
+
+    let mut db = mm.db(ctx).await?;
+
+    let mut fields = vec![("deleted_at", NO_TIME).into()];
+    add_timestamps_for_update(&mut fields, ctx.user_id());
+
+    let count = scoped::<MC, _>(
+        ctx,
+        sqlb::update()
+            .table(MC::TABLE)
+            .and_where("id", "=", id)
+            .and_where("deleted_at", OP_IS_NOT, NO_TIME)
+            .data(fields),
+        true,
+    )
+    .exec(&mut *db)
+    .await?;
+    drop(db);
 
     if count == 0 {
-        return Err(Error::EntityNotFound {
+        return Err(not_found_error::<MC>(ctx, mm, id, true).await?);
+    }
+
+    let scope = event_scope::<MC>(ctx, mm, id).await?;
+    publish_event::<MC>(mm, id, scope, ModelEventKind::Restored);
+
+    Ok(())
+}
+
+/// Deletes the entity for good, soft deleted or not.
+pub async fn purge<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+where
+    MC: DbBmc,
+{
+    check_workspace::<MC>(ctx)?;
+
+    // With the move of its children, for a `TREE` entity.
+    mm.transaction(|mm| async move {
+        // Read before the row is gone.
+        let scope = event_scope::<MC>(ctx, &mm, id).await?;
+        move_children::<MC>(ctx, &mm, id).await?;
+
+        let mut db = mm.db(ctx).await?;
+
+        let count = scoped::<MC, _>(
+            ctx,
+            sqlb::delete().table(MC::TABLE).and_where("id", "=", id),
+            true,
+        )
+        .exec(&mut *db)
+        .await?;
+        drop(db);
+
+        if count == 0 {
+            return Err(not_found_error::<MC>(ctx, &mm, id, true).await?);
+        }
+
+        publish_event::<MC>(&mm, id, scope, ModelEventKind::Purged);
+
+        Ok(())
+    })
+    .await
+}
+
+/// Entity `id` and its descendants, of a `TREE` entity, the parents
+/// before their children (by depth, then id).
+pub async fn get_subtree<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<E>>
+where
+    MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+    E: HasFields,
+{
+    // Not found or access denied.
+    get::<MC, E>(ctx, mm, id).await?;
+
+    let mut db = mm.db(ctx).await?;
+
+    let sql = format!(
+        "{} SELECT {} FROM \"{}\" JOIN subtree USING (\"id\") ORDER BY subtree.depth, \"id\"",
+        subtree_sql::<MC>(ctx, false),
+        columns_sql(E::field_names()),
+        MC::TABLE
+    );
+    let entities = sqlx::query_as(&sql).bind(id).fetch_all(&mut *db).await?;
+
+    Ok(entities)
+}
+
+/// Counts of the descendants of the entity `id` matching the
+/// `condition_sql` (trusted SQL, not escaped), and of all of them, of a
+/// `TREE` entity. Both `0` for an entity not found or not accessible.
+pub async fn count_descendants<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+    condition_sql: &str,
+) -> Result<(i64, i64)>
+where
+    MC: DbBmc,
+{
+    let mut db = mm.db(ctx).await?;
+
+    let sql = format!(
+        "{} SELECT COUNT(*) FILTER (WHERE {condition_sql}), COUNT(*) FROM \"{}\" \
+         JOIN subtree USING (\"id\") WHERE subtree.depth > 0",
+        subtree_sql::<MC>(ctx, false),
+        MC::TABLE
+    );
+    let counts = sqlx::query_as(&sql).bind(id).fetch_one(&mut *db).await?;
+
+    Ok(counts)
+}
+
+/// Moves the entity `id` under `parent_id`, or to the roots when `None`,
+/// of a `TREE` entity.
+///
+/// A parent not found, or being the entity or one of its descendants
+/// (which would make a cycle), fails with a `ValidationFailed` on
+/// `parent_id`.
+pub async fn move_to_parent<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+    parent_id: Option<i64>,
+) -> Result<()>
+where
+    MC: DbBmc,
+{
+    check_workspace::<MC>(ctx)?;
+
+    mm.transaction(|mm| async move {
+        if let Some(parent_id) = parent_id {
+            let mut db = mm.db(ctx).await?;
+
+            // The moves are serialized, so that two concurrent ones cannot
+            // make a cycle the check of each one misses.
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0))")
+                .bind(MC::TABLE)
+                .execute(&mut *db)
+                .await?;
+
+            let sql = format!(
+                "SELECT \"id\" FROM \"{}\" WHERE \"id\" = $1{}",
+                MC::TABLE,
+                scope_sql::<MC>(ctx, false)
+            );
+            let parent: Option<(i64,)> = sqlx::query_as(&sql)
+                .bind(parent_id)
+                .fetch_optional(&mut *db)
+                .await?;
+            if parent.is_none() {
+                return Err(parent_error::<MC>("reference", "parent_id not found"));
+            }
+
+            let sql = format!(
+                "{} SELECT EXISTS (SELECT 1 FROM subtree WHERE \"id\" = $2)",
+                subtree_sql::<MC>(ctx, true)
+            );
+            let (in_subtree,): (bool,) = sqlx::query_as(&sql)
+                .bind(id)
+                .bind(parent_id)
+                .fetch_one(&mut *db)
+                .await?;
+            if in_subtree {
+                return Err(parent_error::<MC>(
+                    "no_cycle",
+                    "must not be the entity or one of its descendants",
+                ));
+            }
+        }
+
+        let parent_u = ParentForMove {
+            parent_id: Some(parent_id),
+        };
+        update::<MC, _>(ctx, &mm, id, parent_u, None).await
+    })
+    .await
+}
+
+/// Parent set by `move_to_parent`, `null` for a root.
+#[derive(Fields)]
+struct ParentForMove {
+    parent_id: Option<Option<i64>>,
+}
+
+impl Validate for ParentForMove {
+    fn validate(&self, _v: &mut Validator) {}
+}
+
+fn parent_error<MC: DbBmc>(rule: &'static str, message: &str) -> Error {
+    validation_failed::<MC>(vec![FieldError {
+        field: "parent_id",
+        rule,
+        message: message.to_string(),
+    }])
+}
+
+/// `subtree` CTE of the `scope_sql` entity `$1` and its descendants, with
+/// their `depth` (0 for `$1`).
+fn subtree_sql<MC: DbBmc>(ctx: &Ctx, include_deleted: bool) -> String {
+    let table = MC::TABLE;
+    // Unqualified, the scope columns are the ones of the table (`subtree`
+    // only has `id` and `depth`).
+    let scope = scope_sql::<MC>(ctx, include_deleted);
+
+    format!(
+        "WITH RECURSIVE subtree AS (\
+         SELECT \"id\", 0 AS depth FROM \"{table}\" WHERE \"id\" = $1{scope} \
+         UNION ALL \
+         SELECT \"{table}\".\"id\", subtree.depth + 1 FROM \"{table}\" \
+         JOIN subtree ON \"{table}\".\"parent_id\" = subtree.\"id\" WHERE TRUE{scope})"
+    )
+}
+
+/// Moves the children of the entity `id` under its parent, for a `TREE`
+/// entity being deleted.
+async fn move_children<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+where
+    MC: DbBmc,
+{
+    if !MC::TREE {
+        return Ok(());
+    }
+
+    let table = MC::TABLE;
+    let version_sql = if MC::VERSIONED {
+        ", \"version\" = \"version\" + 1"
+    } else {
+        ""
+    };
+    let sql = format!(
+        "UPDATE \"{table}\" \
+         SET \"parent_id\" = (SELECT \"parent_id\" FROM \"{table}\" WHERE \"id\" = $1), \
+         \"mid\" = $2, \"mtime\" = $3{version_sql} \
+         WHERE \"parent_id\" = $1{} RETURNING \"id\", {}",
+        scope_sql::<MC>(ctx, true),
+        scope_columns_sql::<MC>()
+    );
+
+    let mut db = mm.db(ctx).await?;
+
+    let rows = sqlx::query(&sql)
+        .bind(id)
+        .bind(ctx.user_id())
+        .bind(now_utc())
+        .fetch_all(&mut *db)
+        .await?;
+    drop(db);
+
+    for row in &rows {
+        let (id, scope) = event_row(row)?;
+        publish_event::<MC>(mm, id, scope, ModelEventKind::Updated);
+    }
+
+    Ok(())
+}
+
+/// Restricts `sb` to the rows the ctx may access, the owned ones for an
+/// `OWNED` entity, the ones of the ctx workspace for a `WORKSPACED` entity
+/// (see `check_workspace`), and excludes the soft deleted rows of a `SOFT_DELETE`
+/// entity unless `include_deleted`.
+fn scoped<'a, MC, SB>(ctx: &Ctx, sb: SB, include_deleted: bool) -> SB
+where
+    MC: DbBmc,
+    SB: Whereable<'a>,
+{
+    let sb = if MC::OWNED && !ctx.is_root() {
+        sb.and_where("owner_id", "=", ctx.user_id())
+    } else {
+        sb
+    };
+
+    let sb = match ctx.workspace_id().filter(|_| MC::WORKSPACED) {
+        Some(workspace_id) => sb.and_where("workspace_id", "=", workspace_id),
+        None => sb,
+    };
+
+    if MC::SOFT_DELETE && !include_deleted {
+        sb.and_where("deleted_at", OP_IS, NO_TIME)
+    } else {
+        sb
+    }
+}
+
+/// SQL condition suffix of `scoped`, for the queries not built by sqlb.
+fn scope_sql<MC: DbBmc>(ctx: &Ctx, include_deleted: bool) -> String {
+    let mut sql = String::new();
+    if MC::OWNED && !ctx.is_root() {
+        // Integers, safe to inline.
+        sql.push_str(&format!(" AND \"owner_id\" = {}", ctx.user_id()));
+    }
+    if let Some(workspace_id) = ctx.workspace_id().filter(|_| MC::WORKSPACED) {
+        sql.push_str(&format!(" AND \"workspace_id\" = {workspace_id}"));
+    }
+    if MC::SOFT_DELETE && !include_deleted {
+        sql.push_str(" AND \"deleted_at\" IS NULL");
+    }
+    sql
+}
+
+/// Error of an entity a `scoped` query did not find, `AccessDenied` when it
+/// exists for another owner, `EntityNotFound` otherwise.
+async fn not_found_error<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+    include_deleted: bool,
+) -> Result<Error>
+where
+    MC: DbBmc,
+{
+    let denied_ids = denied_ids::<MC>(ctx, mm, &[id], include_deleted).await?;
+
+    Ok(missing_error::<MC>(id, denied_ids.contains(&id)))
+}
+
+/// Ids among the `ids` a `scoped` query did not find, which exist for
+/// another owner.
+async fn denied_ids<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    ids: &[i64],
+    include_deleted: bool,
+) -> Result<HashSet<i64>>
+where
+    MC: DbBmc,
+{
+    if !MC::OWNED || ctx.is_root() || ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    // As root, the database row level security hides the other owners rows.
+    // Still in the ctx workspace, the other workspaces rows are not found.
+    let root_ctx = Ctx::root_ctx().with_workspace_id(ctx.workspace_id());
+    let mut db = mm.db(&root_ctx).await?;
+
+    let sql = format!(
+        "SELECT \"id\" FROM \"{}\" WHERE \"id\" = ANY($1){}",
+        MC::TABLE,
+        scope_sql::<MC>(&root_ctx, include_deleted)
+    );
+    let ids: Vec<(i64,)> = sqlx::query_as(&sql).bind(ids).fetch_all(&mut *db).await?;
+
+    Ok(ids.into_iter().map(|(id,)| id).collect())
+}
+
+fn missing_error<MC: DbBmc>(id: i64, denied: bool) -> Error {
+    if denied {
+        Error::AccessDenied {
             entity: MC::TABLE,
             id,
-        });
+        }
+    } else {
+        Error::EntityNotFound {
+            entity: MC::TABLE,
+            id,
+        }
+    }
+}
+
+/// Per id results of a bulk statement on `ids`, which affected the `done`
+/// (id, event scope) rows.
+async fn bulk_results<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    ids: &[i64],
+    done: &[(i64, EventScope)],
+) -> Result<Vec<Result<i64>>>
+where
+    MC: DbBmc,
+{
+    let done_ids: HashSet<i64> = done.iter().map(|(id, _)| *id).collect();
+    let missing_ids: Vec<i64> = ids
+        .iter()
+        .copied()
+        .filter(|id| !done_ids.contains(id))
+        .collect();
+    let denied_ids = denied_ids::<MC>(ctx, mm, &missing_ids, false).await?;
+
+    Ok(ids
+        .iter()
+        .map(|&id| {
+            if done_ids.contains(&id) {
+                Ok(id)
+            } else {
+                Err(missing_error::<MC>(id, denied_ids.contains(&id)))
+            }
+        })
+        .collect())
+}
+
+/// Publishes the event of the entity `id`, if `PUBLISH_EVENTS`.
+fn publish_event<MC: DbBmc>(mm: &ModelManager, id: i64, scope: EventScope, kind: ModelEventKind) {
+    if MC::PUBLISH_EVENTS {
+        mm.publish_event(MC::TABLE, id, scope, kind);
+    }
+}
+
+/// Owner and workspace of the entity `id` for its events, see `ctx_scope`.
+///
+/// Only read from the database for the root ctx, which may change the
+/// entities of any owner, and of any workspace without a ctx workspace.
+async fn event_scope<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<EventScope>
+where
+    MC: DbBmc,
+{
+    let any_workspace = MC::WORKSPACED && ctx.workspace_id().is_none();
+    if !ctx.is_root() || !(MC::OWNED || any_workspace) {
+        return Ok(ctx_scope::<MC>(ctx, id));
+    }
+
+    let mut db = mm.db(ctx).await?;
+
+    let sql = format!(
+        "SELECT \"id\", {} FROM \"{}\" WHERE \"id\" = $1",
+        scope_columns_sql::<MC>(),
+        MC::TABLE
+    );
+    let row = sqlx::query(&sql).bind(id).fetch_optional(&mut *db).await?;
+
+    match row {
+        Some(row) => Ok(event_row(&row)?.1),
+        None => Ok(ctx_scope::<MC>(ctx, id)),
+    }
+}
+
+/// Event scope of the entity `id` changed by the ctx: the ctx user as the
+/// owner of an `OWNED` entity, the ctx workspace for a `WORKSPACED` one, and
+/// the entity itself for a workspace.
+fn ctx_scope<MC: DbBmc>(ctx: &Ctx, id: i64) -> EventScope {
+    let workspace_id = if MC::IS_WORKSPACE {
+        Some(id)
+    } else {
+        ctx.workspace_id().filter(|_| MC::WORKSPACED)
+    };
+
+    EventScope {
+        owner_id: MC::OWNED.then_some(ctx.user_id()),
+        workspace_id,
+    }
+}
+
+/// SQL returning the owner id and the workspace id of a row, for its event
+/// scope (`NULL` when not `OWNED`, or not of a workspace), see `event_row`.
+fn scope_columns_sql<MC: DbBmc>() -> String {
+    let owner_sql = if MC::OWNED {
+        "\"owner_id\""
+    } else {
+        "NULL::BIGINT"
+    };
+    let workspace_sql = if MC::IS_WORKSPACE {
+        "\"id\""
+    } else if MC::WORKSPACED {
+        "\"workspace_id\""
+    } else {
+        "NULL::BIGINT"
+    };
+
+    format!("{owner_sql}, {workspace_sql}")
+}
+
+/// (id, event scope) of a row returning the id, then the
+/// `scope_columns_sql`.
+fn event_row(row: &PgRow) -> Result<(i64, EventScope)> {
+    let scope = EventScope {
+        owner_id: row.try_get(1)?,
+        workspace_id: row.try_get(2)?,
+    };
+
+    Ok((row.try_get(0)?, scope))
+}
+
+/// Indexes of the `rows` (to insert) with a `REFERENCES` column not
+/// referencing an accessible entity, with the column.
+async fn invalid_references<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    rows: &[Vec<Field<'_>>],
+) -> Result<Vec<(usize, &'static str)>>
+where
+    MC: DbBmc,
+{
+    let mut invalid = Vec::new();
+
+    for reference in MC::REFERENCES {
+        let mut binding_idx = 1;
+        let mut values = Vec::new();
+        let mut bound = Vec::new();
+        for (idx, row) in rows.iter().enumerate() {
+            let find = |column: &str| row.iter().find(|field| field.name == column);
+            // Like the foreign key, not checked with a null column.
+            let Some(fields) = std::iter::once(reference.column)
+                .chain(reference.with.iter().copied())
+                .map(find)
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+
+            let row_values: Vec<String> = fields
+                .iter()
+                .map(|field| value_sql(field, &mut binding_idx))
+                .collect();
+            values.push(format!("({idx}, {})", row_values.join(", ")));
+            bound.extend(fields);
+        }
+        if values.is_empty() {
+            continue;
+        }
+
+        let (table, scope) = (reference.target)(ctx);
+        let with_columns: String = (0..reference.with.len())
+            .map(|i| format!(", with_{i}"))
+            .collect();
+        let with_conds: String = reference
+            .with
+            .iter()
+            .enumerate()
+            .map(|(i, column)| format!(" AND \"{column}\" = r.with_{i}"))
+            .collect();
+        // Unqualified, the subquery columns are the ones of its table.
+        let sql = format!(
+            "SELECT r.idx FROM (VALUES {}) AS r(idx, ref_id{with_columns}) \
+             WHERE r.ref_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM \"{table}\" \
+             WHERE \"id\" = r.ref_id{with_conds}{scope})",
+            values.join(", ")
+        );
+
+        let mut db = mm.db(ctx).await?;
+        let rows = bind_fields(sqlx::query(&sql), bound)
+            .fetch_all(&mut *db)
+            .await?;
+        for row in rows {
+            invalid.push((row.try_get::<i32, _>(0)? as usize, reference.column));
+        }
+    }
+
+    Ok(invalid)
+}
+
+/// Ids of the entities of `ids` the `fields` (of an update) would give a
+/// `REFERENCES` column not referencing an accessible entity, with the column.
+async fn invalid_reference_ids<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    ids: &[i64],
+    fields: &[Field<'_>],
+) -> Result<Vec<(i64, &'static str)>>
+where
+    MC: DbBmc,
+{
+    let mut invalid = Vec::new();
+
+    for reference in MC::REFERENCES {
+        let Some(field) = fields.iter().find(|field| field.name == reference.column) else {
+            continue;
+        };
+
+        let (table, scope) = (reference.target)(ctx);
+        let with_conds: String = reference
+            .with
+            .iter()
+            .map(|column| format!(" AND \"{column}\" = e.\"{column}\""))
+            .collect();
+        let mut binding_idx = 1;
+        let ref_sql = value_sql(field, &mut binding_idx);
+        // Like the foreign key, not checked with a null reference; the rows
+        // keeping their reference are left to the foreign key.
+        // Unqualified, the columns are the ones of the table of their query.
+        let sql = format!(
+            "SELECT e.\"id\" FROM \"{}\" e WHERE e.\"id\" = ANY(${binding_idx}){} \
+             AND {ref_sql} IS NOT NULL AND e.\"{}\" IS DISTINCT FROM {ref_sql} AND NOT EXISTS (\
+             SELECT 1 FROM \"{table}\" WHERE \"id\" = {ref_sql}{with_conds}{scope})",
+            MC::TABLE,
+            scope_sql::<MC>(ctx, false),
+            reference.column
+        );
+
+        let mut db = mm.db(ctx).await?;
+        let rows = bind_fields(sqlx::query(&sql), [field])
+            .bind(ids)
+            .fetch_all(&mut *db)
+            .await?;
+        for row in rows {
+            invalid.push((row.try_get::<i64, _>(0)?, reference.column));
+        }
+    }
+
+    Ok(invalid)
+}
+
+/// Sets the `fields` of the `scoped` rows of `ids`, returning the (id,
+/// event scope) of the updated rows.
+async fn update_ids<MC>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    ids: &[i64],
+    fields: &[Field<'_>],
+) -> Result<Vec<(i64, EventScope)>>
+where
+    MC: DbBmc,
+{
+    let mut binding_idx = 1;
+    let sets = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "\"{}\" = {}",
+                field.name,
+                value_sql(field, &mut binding_idx)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let sql = format!(
+        "UPDATE \"{}\" SET {sets} WHERE \"id\" = ANY(${binding_idx}){} RETURNING \"id\", {}",
+        MC::TABLE,
+        scope_sql::<MC>(ctx, false),
+        scope_columns_sql::<MC>()
+    );
+
+    let mut db = mm.db(ctx).await?;
+
+    let query = bind_fields(sqlx::query(&sql), fields).bind(ids);
+    let rows = query
+        .fetch_all(&mut *db)
+        .await
+        .map_err(reference_sqlx_error::<MC>)?;
+
+    rows.iter().map(event_row).collect()
+}
+
+/// SQL of a field value, its raw SQL, or the next `$n` placeholder.
+fn value_sql(field: &Field, binding_idx: &mut usize) -> String {
+    match field.value.raw() {
+        Some(raw) => raw.to_string(),
+        None => {
+            let placeholder = format!("${binding_idx}");
+            *binding_idx += 1;
+            placeholder
+        }
+    }
+}
+
+/// Binds the values of the `fields`, in the `value_sql` placeholders order.
+fn bind_fields<'q, 'a: 'q>(
+    mut query: PgQuery<'q>,
+    fields: impl IntoIterator<Item = &'q Field<'a>>,
+) -> PgQuery<'q> {
+    for field in fields {
+        if field.value.raw().is_none() {
+            query = field.value.bind_query(query);
+        }
+    }
+    query
+}
+
+fn columns_sql(columns: &[&str]) -> String {
+    columns
+        .iter()
+        .map(|name| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn check_bulk_size<MC: DbBmc>(field: &'static str, len: usize) -> Result<()> {
+    if len > BULK_MAX {
+        return Err(validation_failed::<MC>(vec![FieldError {
+            field,
+            rule: "max_items",
+            message: format!("at most {BULK_MAX} items"),
+        }]));
+    }
+
+    Ok(())
+}
+
+/// Fields the entities can be filtered by, their own and the related ones.
+fn filter_field_names<MC: DbBmc, E: HasFields>() -> Vec<&'static str> {
+    E::field_names()
+        .iter()
+        .copied()
+        .chain(MC::RELATED_FIELDS.iter().map(|(field, ..)| *field))
+        .collect()
+}
+
+/// Adds the owner of a new `OWNED` entity, the ctx user, and the workspace
+/// of a new `WORKSPACED` entity, the ctx one (required, even for root).
+fn add_scope_for_create<MC: DbBmc>(fields: &mut Vec<Field>, ctx: &Ctx) -> Result<()> {
+    if MC::OWNED {
+        fields.push(("owner_id", ctx.user_id()).into());
+    }
+
+    if MC::WORKSPACED {
+        let workspace_id = ctx
+            .workspace_id()
+            .ok_or(Error::WorkspaceRequired { entity: MC::TABLE })?;
+        fields.push(("workspace_id", workspace_id).into());
+    }
+
+    Ok(())
+}
+
+/// Fails for a `WORKSPACED` entity when the ctx has no workspace, unless
+/// root ctx.
+fn check_workspace<MC: DbBmc>(ctx: &Ctx) -> Result<()> {
+    if MC::WORKSPACED && ctx.workspace_id().is_none() && !ctx.is_root() {
+        return Err(Error::WorkspaceRequired { entity: MC::TABLE });
     }
 
     Ok(())
 }
+
+/// Adds the audit fields of a new entity, created by the `user_id` now.
+pub fn add_timestamps_for_create(fields: &mut Vec<Field>, user_id: i64) {
+    let now = now_utc();
+    fields.push(("cid", user_id).into());
+    fields.push(("ctime", now).into());
+    fields.push(("mid", user_id).into());
+    fields.push(("mtime", now).into());
+}
+
+/// Adds the audit fields of an entity modified by the `user_id` now.
+pub fn add_timestamps_for_update(fields: &mut Vec<Field>, user_id: i64) {
+    fields.push(("mid", user_id).into());
+    fields.push(("mtime", now_utc()).into());
+}
+
+/// Filter values of a type not comparable with their field, which the
+/// validation cannot catch, are reported as a validation error.
+fn filter_sqlx_error<MC: DbBmc>(ex: sqlx::Error) -> Error {
+    // undefined_function (no operator for the types), invalid_text_representation
+    const TYPE_MISMATCH_CODES: [&str; 2] = ["42883", "22P02"];
+
+    let is_type_mismatch = ex
+        .as_database_error()
+        .and_then(|db_error| db_error.code())
+        .is_some_and(|code| TYPE_MISMATCH_CODES.contains(&code.as_ref()));
+
+    if is_type_mismatch {
+        validation_failed::<MC>(vec![FieldError {
+            field: "filters",
+            rule: "filter_value",
+            message: "value type does not match its field".to_string(),
+        }])
+    } else {
+        Error::Sqlx(ex)
+    }
+}
+
+/// Violations of the foreign keys of `REFERENCES` are reported as a
+/// validation error of their column.
+fn reference_sqlx_error<MC: DbBmc>(ex: sqlx::Error) -> Error {
+    // foreign_key_violation
+    const FOREIGN_KEY_VIOLATION_CODE: &str = "23503";
+
+    let reference = ex
+        .as_database_error()
+        .filter(|db_error| db_error.code().as_deref() == Some(FOREIGN_KEY_VIOLATION_CODE))
+        .and_then(|db_error| db_error.constraint())
+        .and_then(|constraint| {
+            MC::REFERENCES
+                .iter()
+                .find(|reference| constraint == format!("{}_{}_fkey", MC::TABLE, reference.column))
+        });
+
+    match reference {
+        Some(reference) => reference_error::<MC>(reference.column),
+        None => Error::Sqlx(ex),
+    }
+}
+
+fn reference_error<MC: DbBmc>(column: &'static str) -> Error {
+    validation_failed::<MC>(vec![FieldError {
+        field: column,
+        rule: "reference",
+        message: format!("{column} not found"),
+    }])
+}
+
+fn validation_failed<MC: DbBmc>(errors: Vec<FieldError>) -> Error {
+    Error::ValidationFailed {
+        entity: MC::TABLE,
+        errors,
+    }
+}
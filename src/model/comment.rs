@@ -0,0 +1,486 @@
+use crate::ctx::Ctx;
+use crate::model::{
+    base::{self, DbBmc, Reference},
+    event::{EventScope, ModelEventKind},
+    filter::{Filter, FilterCond, FilterOp},
+    list_options::{ListOptions, Page},
+    task::TaskBmc,
+    validation::{Validate, Validator},
+    Error, ModelManager, Result,
+};
+use crate::util::now_utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlb::Fields;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, Fields, FromRow, Serialize, JsonSchema)]
+pub struct Comment {
+    pub id: i64,
+    pub workspace_id: i64,
+    pub task_id: i64,
+    /// Comment replied to, of the same task.
+    pub parent_id: Option<i64>,
+    pub author_id: i64,
+    pub content: String,
+    /// Last edit of the content, `None` if never edited.
+    #[serde(with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub edited_at: Option<OffsetDateTime>,
+
+    pub cid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub mtime: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CommentForCreate {
+    pub task_id: i64,
+    pub parent_id: Option<i64>,
+    pub content: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CommentForUpdate {
+    pub content: String,
+}
+
+/// Inserted comment, authored by the ctx user.
+#[derive(Fields)]
+struct CommentForInsert {
+    task_id: i64,
+    parent_id: Option<i64>,
+    author_id: i64,
+    content: String,
+}
+
+/// Edited content, with its edit time.
+#[derive(Fields)]
+struct CommentForEdit {
+    content: String,
+    edited_at: OffsetDateTime,
+}
+
+const CONTENT_MAX_CHARS: usize = 10_000;
+
+impl Validate for CommentForInsert {
+    fn validate(&self, v: &mut Validator) {
+        validate_content(v, &self.content);
+    }
+}
+
+impl Validate for CommentForEdit {
+    fn validate(&self, v: &mut Validator) {
+        validate_content(v, &self.content);
+    }
+}
+
+fn validate_content(v: &mut Validator, content: &str) {
+    v.not_blank("content", content)
+        .max_chars("content", content, CONTENT_MAX_CHARS);
+}
+
+pub struct CommentBmc;
+
+impl DbBmc for CommentBmc {
+    const TABLE: &'static str = "comments";
+    const SOFT_DELETE: bool = true;
+    const WORKSPACED: bool = true;
+    // Scoped as their task, see `task_scope`.
+    const PUBLISH_EVENTS: bool = false;
+    const REFERENCES: &'static [Reference] =
+        &[Reference::to::<CommentBmc>("parent_id", &["task_id"])];
+    const COLUMN_TYPES: &'static [(&'static str, &'static str)] = &[
+        ("edited_at", "timestamptz"),
+        ("deleted_at", "timestamptz"),
+        ("ctime", "timestamptz"),
+        ("mtime", "timestamptz"),
+    ];
+}
+
+impl CommentBmc {
+    /// Creates a comment of the ctx user on the task, a reply when
+    /// `parent_id` is set (to a comment of the task not deleted).
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, comment_c: CommentForCreate) -> Result<i64> {
+        let scope = Self::task_scope(ctx, mm, comment_c.task_id).await?;
+
+        let CommentForCreate {
+            task_id,
+            parent_id,
+            content,
+        } = comment_c;
+        let comment_i = CommentForInsert {
+            task_id,
+            parent_id,
+            author_id: ctx.user_id(),
+            content,
+        };
+
+        let id = base::create::<Self, _>(ctx, mm, comment_i).await?;
+        mm.publish_event(Self::TABLE, id, scope, ModelEventKind::Created);
+
+        Ok(id)
+    }
+
+    /// Comment `id`, of a task of the ctx workspace.
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Comment> {
+        let comment: Comment = base::get::<Self, _>(ctx, mm, id).await?;
+        // Access check.
+        Self::task_scope(ctx, mm, comment.task_id).await?;
+
+        Ok(comment)
+    }
+
+    /// Comment `id`, a tombstone if deleted.
+    pub async fn get_include_deleted(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Comment> {
+        let comment: Comment = base::get_include_deleted::<Self, _>(ctx, mm, id).await?;
+        // Access check.
+        Self::task_scope(ctx, mm, comment.task_id).await?;
+
+        Ok(tombstone(ctx, comment))
+    }
+
+    /// Lists the comments of the task, replies included (by `parent_id`).
+    ///
+    /// The deleted comments are listed as tombstones, their content being
+    /// withdrawn by their author (except for root), so their replies keep
+    /// their parent. A filter on `content` matches no tombstone.
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        task_id: i64,
+        mut filters: Vec<Filter>,
+        mut list_options: ListOptions,
+    ) -> Result<Page<Comment>> {
+        // Access check.
+        Self::task_scope(ctx, mm, task_id).await?;
+
+        list_options.include_deleted = Some(true);
+        if !ctx.is_root() && filters.iter().any(|filter| filter.has_field("content")) {
+            filters.push(Filter::Cond(FilterCond {
+                field: "deleted_at".to_string(),
+                op: FilterOp::Null,
+                value: true.into(),
+            }));
+        }
+
+        filters.push(Filter::Cond(FilterCond {
+            field: "task_id".to_string(),
+            op: FilterOp::Eq,
+            value: task_id.into(),
+        }));
+
+        let mut page: Page<Comment> = base::list::<Self, _>(ctx, mm, filters, list_options).await?;
+        page.items = page
+            .items
+            .into_iter()
+            .map(|comment| tombstone(ctx, comment))
+            .collect();
+
+        Ok(page)
+    }
+
+    /// Edits the content of a comment of the ctx user, marked as edited.
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        comment_u: CommentForUpdate,
+    ) -> Result<()> {
+        let scope = Self::check_author(ctx, mm, id).await?;
+
+        let comment_e = CommentForEdit {
+            content: comment_u.content,
+            edited_at: now_utc(),
+        };
+
+        base::update::<Self, _>(ctx, mm, id, comment_e, None).await?;
+        mm.publish_event(Self::TABLE, id, scope, ModelEventKind::Updated);
+
+        Ok(())
+    }
+
+    /// Soft deletes a comment of the ctx user, its replies are kept.
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let scope = Self::check_author(ctx, mm, id).await?;
+
+        base::delete::<Self>(ctx, mm, id).await?;
+        mm.publish_event(Self::TABLE, id, scope, ModelEventKind::Deleted);
+
+        Ok(())
+    }
+
+    /// Owner and workspace of the task `task_id` of comments, the scope of
+    /// their events, `EntityNotFound` if deleted or not in the ctx workspace.
+    ///
+    /// The comments are shared by the members of the workspace (the ctx
+    /// user being one of them, see `mw_ctx_resolve`), whoever owns the task.
+    async fn task_scope(ctx: &Ctx, mm: &ModelManager, task_id: i64) -> Result<EventScope> {
+        if ctx.workspace_id().is_none() && !ctx.is_root() {
+            return Err(Error::WorkspaceRequired {
+                entity: Self::TABLE,
+            });
+        }
+
+        // Tasks of all the owners, in the ctx workspace.
+        let root_ctx = Ctx::root_ctx().with_workspace_id(ctx.workspace_id());
+        let mut db = mm.db(&root_ctx).await?;
+        let task: Option<(i64, i64)> = sqlx::query_as(&format!(
+            "SELECT owner_id, workspace_id FROM {} WHERE id = $1 AND deleted_at IS NULL",
+            TaskBmc::TABLE
+        ))
+        .bind(task_id)
+        .fetch_optional(&mut *db)
+        .await?;
+
+        match task {
+            Some((owner_id, workspace_id)) => Ok(EventScope {
+                owner_id: Some(owner_id),
+                workspace_id: Some(workspace_id),
+            }),
+            None => Err(Error::EntityNotFound {
+                entity: TaskBmc::TABLE,
+                id: task_id,
+            }),
+        }
+    }
+
+    /// Fails with `AccessDenied` if the comment is not of the ctx user
+    /// (except for root), otherwise returns its `task_scope`.
+    async fn check_author(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<EventScope> {
+        let comment: Comment = base::get::<Self, _>(ctx, mm, id).await?;
+        let scope = Self::task_scope(ctx, mm, comment.task_id).await?;
+
+        if comment.author_id != ctx.user_id() && !ctx.is_root() {
+            return Err(Error::AccessDenied {
+                entity: Self::TABLE,
+                id,
+            });
+        }
+
+        Ok(scope)
+    }
+}
+
+/// Blanks the content of a deleted comment, except for root.
+fn tombstone(ctx: &Ctx, mut comment: Comment) -> Comment {
+    if comment.deleted_at.is_some() && !ctx.is_root() {
+        comment.content = String::new();
+    }
+
+    comment
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+
+    use super::*;
+    use crate::model::workspace::WorkspaceBmc;
+    use anyhow::Result;
+    use serde_json::json;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_reply_edit_delete_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let fx_tasks = _dev_utils::seed_test(
+            &ctx,
+            &mm,
+            &[
+                "test_reply_edit_delete_ok 01",
+                "test_reply_edit_delete_ok 02",
+            ],
+        )
+        .await?;
+        let fx_task_id = fx_tasks[0].id;
+
+        let comment_id = CommentBmc::create(
+            &ctx,
+            &mm,
+            CommentForCreate {
+                task_id: fx_task_id,
+                parent_id: None,
+                content: "first".to_string(),
+            },
+        )
+        .await?;
+        let reply_id = CommentBmc::create(
+            &ctx,
+            &mm,
+            CommentForCreate {
+                task_id: fx_task_id,
+                parent_id: Some(comment_id),
+                content: "reply".to_string(),
+            },
+        )
+        .await?;
+
+        // Parent of another task.
+        let res = CommentBmc::create(
+            &ctx,
+            &mm,
+            CommentForCreate {
+                task_id: fx_tasks[1].id,
+                parent_id: Some(comment_id),
+                content: "misplaced reply".to_string(),
+            },
+        )
+        .await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "parent_id"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        CommentBmc::update(
+            &ctx,
+            &mm,
+            comment_id,
+            CommentForUpdate {
+                content: "first edited".to_string(),
+            },
+        )
+        .await?;
+        let comment = CommentBmc::get(&ctx, &mm, comment_id).await?;
+        assert_eq!(comment.content, "first edited");
+        assert_eq!(comment.author_id, 1000);
+        assert!(comment.edited_at.is_some(), "marked as edited");
+
+        // Another member of the workspace, on the task of demo1.
+        let ctx_other = _dev_utils::ctx_test(1001);
+        WorkspaceBmc::add_member(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID, 1001).await?;
+        let mut events = mm.subscribe_events();
+        let other_id = CommentBmc::create(
+            &ctx_other,
+            &mm,
+            CommentForCreate {
+                task_id: fx_task_id,
+                parent_id: Some(comment_id),
+                content: "other reply".to_string(),
+            },
+        )
+        .await?;
+        let event = events.try_recv()?;
+        assert_eq!((event.entity, event.entity_id), ("comments", other_id));
+        assert_eq!(event.owner_id, Some(1000), "scoped as the task");
+        let comment = CommentBmc::get(&ctx_other, &mm, comment_id).await?;
+        assert_eq!(comment.content, "first edited");
+        let res = CommentBmc::update(
+            &ctx_other,
+            &mm,
+            comment_id,
+            CommentForUpdate {
+                content: "not mine".to_string(),
+            },
+        )
+        .await;
+        assert!(
+            matches!(res, Err(Error::AccessDenied { entity: "comments", id }) if id == comment_id),
+            "AccessDenied not matching: {res:?}"
+        );
+        let res = CommentBmc::delete(&ctx, &mm, other_id).await;
+        assert!(
+            matches!(res, Err(Error::AccessDenied { entity: "comments", id }) if id == other_id),
+            "AccessDenied not matching: {res:?}"
+        );
+        CommentBmc::delete(&ctx_other, &mm, other_id).await?;
+        WorkspaceBmc::remove_member(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID, 1001).await?;
+
+        // Task not found.
+        let res = CommentBmc::create(
+            &ctx,
+            &mm,
+            CommentForCreate {
+                task_id: 999_999,
+                parent_id: None,
+                content: "lost".to_string(),
+            },
+        )
+        .await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::EntityNotFound {
+                    entity: "tasks",
+                    id: 999_999
+                })
+            ),
+            "EntityNotFound not matching: {res:?}"
+        );
+
+        CommentBmc::delete(&ctx, &mm, comment_id).await?;
+        let res = CommentBmc::create(
+            &ctx,
+            &mm,
+            CommentForCreate {
+                task_id: fx_task_id,
+                parent_id: Some(comment_id),
+                content: "reply to deleted".to_string(),
+            },
+        )
+        .await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "parent_id"),
+            "ValidationFailed not matching: {res:?}"
+        );
+        let page = CommentBmc::list(&ctx, &mm, fx_task_id, vec![], Default::default()).await?;
+        let comments: Vec<(i64, Option<i64>, &str, bool)> = page
+            .items
+            .iter()
+            .map(|c| {
+                (
+                    c.id,
+                    c.parent_id,
+                    c.content.as_str(),
+                    c.deleted_at.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                (comment_id, None, "", true),
+                (reply_id, Some(comment_id), "reply", false),
+                (other_id, Some(comment_id), "", true),
+            ],
+            "tombstones of the deleted comments"
+        );
+        let page = CommentBmc::list(
+            &ctx,
+            &mm,
+            fx_task_id,
+            serde_json::from_value(json!([
+                {"field": "content", "op": "contains", "value": "first"},
+            ]))?,
+            Default::default(),
+        )
+        .await?;
+        assert_eq!(page.total, 0, "deleted content not matched");
+        let comment = CommentBmc::get_include_deleted(&Ctx::root_ctx(), &mm, comment_id).await?;
+        assert_eq!(comment.content, "first edited", "content kept for root");
+
+        // Purging the task deletes its comments.
+        for task in fx_tasks {
+            TaskBmc::purge(&ctx, &mm, task.id).await?;
+        }
+        let res = CommentBmc::get_include_deleted(&ctx, &mm, reply_id).await;
+        assert!(
+            matches!(res, Err(Error::EntityNotFound { .. })),
+            "EntityNotFound not matching: {res:?}"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+use subtle::ConstantTimeEq;
+
+use crate::config;
+use crate::crypt::{encrypt_into_b64u, EncryptContent};
+use crate::model::filter::{column_type, push_value};
+use crate::model::list_options::OrderDirection;
+use crate::model::Result;
+use crate::util::{b64u_decode, b64u_encode};
+
+/// Position after an entity in a list, by the values of its sort key.
+///
+/// The cursor token is `b64u(json content).signature`, signed with the
+/// entity name as salt, so it cannot be forged nor used for another entity.
+#[derive(Serialize, Deserialize)]
+struct CursorContent {
+    /// Sort key fields, so a cursor is only used with its own ordering.
+    fields: Vec<String>,
+    values: Vec<Value>,
+}
+
+/// Cursor token after `entity_value` (the serialized entity) in a list
+/// ordered by `sort_key`.
+pub(in crate::model) fn encode_cursor(
+    entity: &str,
+    sort_key: &[(&str, OrderDirection)],
+    entity_value: &Value,
+) -> Result<String> {
+    let content = CursorContent {
+        fields: sort_key_fields(sort_key),
+        values: sort_key
+            .iter()
+            .map(|(field, _)| entity_value.get(field).cloned().unwrap_or(Value::Null))
+            .collect(),
+    };
+
+    let content_b64u = b64u_encode(&serde_json::to_string(&content).unwrap_or_default());
+    let sign_b64u = sign_cursor(entity, &content_b64u)?;
+
+    Ok(format!("{content_b64u}.{sign_b64u}"))
+}
+
+/// Sort key values of a `cursor` token, or `None` if it is invalid, or
+/// was not made for this `entity` and `sort_key`.
+pub(in crate::model) fn decode_cursor(
+    entity: &str,
+    sort_key: &[(&str, OrderDirection)],
+    cursor: &str,
+) -> Result<Option<Vec<Value>>> {
+    let Some((content_b64u, sign_b64u)) = cursor.split_once('.') else {
+        return Ok(None);
+    };
+
+    // Constant time, not to tell how much of a forged signature matches.
+    let sign_expected = sign_cursor(entity, content_b64u)?;
+    if !bool::from(sign_expected.as_bytes().ct_eq(sign_b64u.as_bytes())) {
+        return Ok(None);
+    }
+
+    let content = b64u_decode(content_b64u)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CursorContent>(&content).ok());
+
+    Ok(content
+        .filter(|content| {
+            content.fields == sort_key_fields(sort_key)
+                && content.values.len() == content.fields.len()
+        })
+        .map(|content| content.values))
+}
+
+/// Pushes the SQL condition selecting the rows after the cursor `values`,
+/// in the `sort_key` order (Postgres default, nulls last when ascending).
+///
+/// The values are cast to their `column_types` type (see
+/// `DbBmc::COLUMN_TYPES`).
+pub(in crate::model) fn push_after_sql(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    sort_key: &[(&str, OrderDirection)],
+    values: &[Value],
+    column_types: &[(&str, &str)],
+) {
+    // (a after va) OR (a = va AND b after vb) OR ...
+    qb.push("(");
+    for i in 0..sort_key.len() {
+        if i > 0 {
+            qb.push(" OR ");
+        }
+
+        qb.push("(");
+        for ((field, _), value) in sort_key[..i].iter().zip(values) {
+            push_equal(qb, field, value, column_type(column_types, field));
+            qb.push(" AND ");
+        }
+        let (field, direction) = sort_key[i];
+        push_after(
+            qb,
+            field,
+            direction,
+            &values[i],
+            column_type(column_types, field),
+        );
+        qb.push(")");
+    }
+    qb.push(")");
+}
+
+fn push_equal(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    field: &str,
+    value: &Value,
+    sql_type: Option<&str>,
+) {
+    if value.is_null() {
+        qb.push(format!("\"{field}\" IS NULL"));
+    } else {
+        qb.push(format!("\"{field}\" = "));
+        push_value(qb, value, sql_type);
+    }
+}
+
+fn push_after(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    field: &str,
+    direction: OrderDirection,
+    value: &Value,
+    sql_type: Option<&str>,
+) {
+    match (direction, value.is_null()) {
+        (OrderDirection::Asc, false) => {
+            qb.push(format!("(\"{field}\" > "));
+            push_value(qb, value, sql_type);
+            qb.push(format!(" OR \"{field}\" IS NULL)"));
+        }
+        (OrderDirection::Asc, true) => {
+            qb.push("FALSE");
+        }
+        (OrderDirection::Desc, false) => {
+            qb.push(format!("\"{field}\" < "));
+            push_value(qb, value, sql_type);
+        }
+        (OrderDirection::Desc, true) => {
+            qb.push(format!("\"{field}\" IS NOT NULL"));
+        }
+    }
+}
+
+fn sort_key_fields(sort_key: &[(&str, OrderDirection)]) -> Vec<String> {
+    sort_key
+        .iter()
+        .map(|(field, direction)| match direction {
+            OrderDirection::Asc => field.to_string(),
+            OrderDirection::Desc => format!("!{field}"),
+        })
+        .collect()
+}
+
+fn sign_cursor(entity: &str, content_b64u: &str) -> Result<String> {
+    let sign_b64u = encrypt_into_b64u(
+        &config().TOKEN_KEY,
+        &EncryptContent {
+            content: content_b64u.to_string(),
+            salt: entity.to_string(),
+        },
+    )?;
+
+    Ok(sign_b64u)
+}
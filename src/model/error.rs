@@ -1,4 +1,7 @@
-use crate::{crypt, model::store};
+use crate::{
+    crypt,
+    model::{store, validation::FieldError},
+};
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
 
@@ -7,9 +10,46 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[serde_as]
 #[derive(Debug, Serialize)]
 pub enum Error {
-    EntityNotFound { entity: &'static str, id: i64 },
+    EntityNotFound {
+        entity: &'static str,
+        id: i64,
+    },
+    /// Entity existing, but not accessible to the ctx user.
+    AccessDenied {
+        entity: &'static str,
+        id: i64,
+    },
+    ValidationFailed {
+        entity: &'static str,
+        errors: Vec<FieldError>,
+    },
+    /// Access to a workspaced entity with a ctx without workspace.
+    WorkspaceRequired {
+        entity: &'static str,
+    },
+    /// Delete of an entity still referenced by other entities.
+    EntityInUse {
+        entity: &'static str,
+        id: i64,
+        by: &'static str,
+    },
+    /// Update of an entity whose version changed since it was read.
+    Conflict {
+        entity: &'static str,
+        id: i64,
+        current: Option<serde_json::Value>,
+    },
+    IdempotencyKeyReused {
+        key: String,
+    },
+    IdempotencyKeyInProgress {
+        key: String,
+    },
+    /// Use of a transactional ModelManager after its transaction ended.
+    TxnEnded,
     Store(store::Error),
     Sqlx(#[serde_as(as = "DisplayFromStr")] sqlx::Error),
+    SerdeJson(String),
     Crypt(crypt::Error),
 }
 
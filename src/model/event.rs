@@ -0,0 +1,248 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::ctx::Ctx;
+use crate::util::now_utc;
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Number of recent events kept to resume a subscription.
+const EVENT_BUFFER_CAPACITY: usize = 1024;
+
+/// Change made on an entity through the model layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelEvent {
+    /// Sequence id, increasing for each event of this ModelManager.
+    pub id: u64,
+    pub entity: &'static str,
+    pub entity_id: i64,
+    /// Owner of the entity, for an entity with owners.
+    pub owner_id: Option<i64>,
+    /// Workspace of the entity, for an entity of a workspace (or a
+    /// workspace itself).
+    pub workspace_id: Option<i64>,
+    pub kind: ModelEventKind,
+}
+
+/// Owner and workspace of a changed entity, restricting who sees its events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventScope {
+    pub owner_id: Option<i64>,
+    pub workspace_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelEventKind {
+    Created,
+    Updated,
+    /// Deleted, or soft deleted (then possibly `Restored` later).
+    Deleted,
+    Restored,
+    /// Soft deleted entity deleted for good.
+    Purged,
+}
+
+impl ModelEvent {
+    /// Whether the ctx user may see this change, the changes of an owned
+    /// entity are only visible to its owner, and the ones of an entity of a
+    /// workspace only in this workspace (all visible to the root ctx).
+    pub fn is_visible_to(&self, ctx: &Ctx) -> bool {
+        if ctx.is_root() {
+            return true;
+        }
+
+        let owner_ok = self
+            .owner_id
+            .is_none_or(|owner_id| owner_id == ctx.user_id());
+        let workspace_ok = self
+            .workspace_id
+            .is_none_or(|workspace_id| ctx.workspace_id() == Some(workspace_id));
+
+        owner_ok && workspace_ok
+    }
+}
+
+/// Broadcasts the model events to the current subscribers, and keeps the
+/// most recent ones so a subscriber can resume after a disconnect.
+#[derive(Clone)]
+pub struct EventHub {
+    tx: broadcast::Sender<ModelEvent>,
+    log: Arc<Mutex<EventLog>>,
+}
+
+struct EventLog {
+    last_id: u64,
+    recent: VecDeque<ModelEvent>,
+}
+
+impl EventHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        // Seeded with the current time, so the ids keep increasing across
+        // restarts and a stale `last_id` replays the whole buffer.
+        let last_id = (now_utc().unix_timestamp_nanos() / 1_000) as u64;
+
+        Self {
+            tx,
+            log: Arc::new(Mutex::new(EventLog {
+                last_id,
+                recent: VecDeque::with_capacity(EVENT_BUFFER_CAPACITY),
+            })),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ModelEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Subscribes, and returns the buffered events after `last_id` (if any),
+    /// so no event is missed nor duplicated between the two.
+    pub fn subscribe_since(
+        &self,
+        last_id: Option<u64>,
+    ) -> (Vec<ModelEvent>, broadcast::Receiver<ModelEvent>) {
+        let log = self.log.lock().unwrap();
+
+        let rx = self.tx.subscribe();
+        let missed = match last_id {
+            Some(last_id) => log
+                .recent
+                .iter()
+                .filter(|event| event.id > last_id)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+
+        (missed, rx)
+    }
+
+    pub fn publish(
+        &self,
+        entity: &'static str,
+        entity_id: i64,
+        scope: EventScope,
+        kind: ModelEventKind,
+    ) {
+        let mut log = self.log.lock().unwrap();
+
+        log.last_id += 1;
+        let event = ModelEvent {
+            id: log.last_id,
+            entity,
+            entity_id,
+            owner_id: scope.owner_id,
+            workspace_id: scope.workspace_id,
+            kind,
+        };
+
+        if log.recent.len() == EVENT_BUFFER_CAPACITY {
+            log.recent.pop_front();
+        }
+        log.recent.push_back(event.clone());
+
+        // Only fails when there is no subscriber, which is fine.
+        let _ = self.tx.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+    use crate::model::project::{ProjectBmc, ProjectForCreate};
+    use crate::model::workspace::{WorkspaceBmc, WorkspaceForCreate};
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[tokio::test]
+    async fn test_subscribe_since_ok() -> Result<()> {
+        let hub = EventHub::new();
+
+        hub.publish("tasks", 1, EventScope::default(), ModelEventKind::Created);
+        let (_, mut rx) = hub.subscribe_since(None);
+        hub.publish("tasks", 1, EventScope::default(), ModelEventKind::Updated);
+        hub.publish("tasks", 2, EventScope::default(), ModelEventKind::Created);
+
+        let first = rx.recv().await?;
+        assert_eq!(first.entity_id, 1);
+        assert!(matches!(first.kind, ModelEventKind::Updated));
+
+        let (missed, _) = hub.subscribe_since(Some(first.id));
+        assert_eq!(missed.len(), 1, "events after the first update");
+        assert_eq!(missed[0].entity_id, 2);
+
+        let (missed, _) = hub.subscribe_since(Some(0));
+        assert_eq!(missed.len(), 3, "stale last_id replays the buffer");
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_workspace_events_not_visible_elsewhere() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx_a = _dev_utils::ctx_test(1000);
+        let mut events = mm.subscribe_events();
+
+        let workspace_b_id = WorkspaceBmc::create(
+            &ctx_a,
+            &mm,
+            WorkspaceForCreate {
+                name: "test_workspace_events_not_visible_elsewhere".to_string(),
+            },
+        )
+        .await?;
+        // Same user, in the workspace B.
+        let ctx_b = ctx_a.clone().with_workspace_id(Some(workspace_b_id));
+        let workspace_event = events.recv().await?;
+        assert_eq!(workspace_event.workspace_id, Some(workspace_b_id));
+
+        let project_id = ProjectBmc::create(
+            &ctx_a,
+            &mm,
+            ProjectForCreate {
+                name: "test_workspace_events_not_visible_elsewhere".to_string(),
+            },
+        )
+        .await?;
+        let project_event = events.recv().await?;
+        assert_eq!(project_event.entity_id, project_id);
+
+        assert!(
+            project_event.is_visible_to(&ctx_a),
+            "visible in its workspace"
+        );
+        assert!(
+            !project_event.is_visible_to(&ctx_b),
+            "not visible in workspace B"
+        );
+        assert!(
+            !workspace_event.is_visible_to(&ctx_a),
+            "not visible in workspace A"
+        );
+        assert!(workspace_event.is_visible_to(&ctx_b));
+        assert!(
+            project_event.is_visible_to(&Ctx::root_ctx()),
+            "visible to root"
+        );
+
+        let mut db = mm.db(&Ctx::root_ctx()).await?;
+        sqlx::query("DELETE FROM projects WHERE id = $1")
+            .bind(project_id)
+            .execute(&mut *db)
+            .await?;
+        sqlx::query("DELETE FROM workspaces WHERE id = $1")
+            .bind(workspace_b_id)
+            .execute(&mut *db)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::model::base::DbBmc;
+use crate::model::validation::Validator;
+
+/// Filter of a `base::list`, a condition on a field, or an AND/OR group of
+/// filters.
+///
+/// ```json
+/// {"or": [
+///     {"field": "title", "op": "contains", "value": "urgent"},
+///     {"field": "id", "op": "in", "value": [1000, 1001]}
+/// ]}
+/// ```
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Filter {
+    And { and: Vec<Filter> },
+    Or { or: Vec<Filter> },
+    Cond(FilterCond),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct FilterCond {
+    pub field: String,
+    pub op: FilterOp,
+    /// Scalar for the comparisons, array for `in`, string for `contains`
+    /// and `startsWith`, and boolean for `null` (`false` for not null).
+    /// RFC 3339 strings for the timestamp fields.
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    In,
+    Contains,
+    StartsWith,
+    Gt,
+    Lt,
+    Null,
+}
+
+impl Filter {
+    /// Checks the fields are among the entity `field_names`, and the
+    /// values match their operator.
+    pub fn validate(&self, v: &mut Validator, field_names: &[&str]) {
+        match self {
+            Filter::And { and: filters } | Filter::Or { or: filters } => {
+                for filter in filters {
+                    filter.validate(v, field_names);
+                }
+            }
+            Filter::Cond(cond) => cond.validate(v, field_names),
+        }
+    }
+
+    /// Whether this filter has a condition on the `field`.
+    pub fn has_field(&self, field: &str) -> bool {
+        match self {
+            Filter::And { and: filters } | Filter::Or { or: filters } => {
+                filters.iter().any(|filter| filter.has_field(field))
+            }
+            Filter::Cond(cond) => cond.field == field,
+        }
+    }
+
+    /// Pushes the SQL condition of this filter on the `MC` entities, with its
+    /// values bound (cast to their `DbBmc::COLUMN_TYPES` type).
+    ///
+    /// Must be validated first, the field names are not escaped.
+    pub(in crate::model) fn push_sql<MC: DbBmc>(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            Filter::And { and: filters } => push_group::<MC>(qb, filters, " AND ", "TRUE"),
+            Filter::Or { or: filters } => push_group::<MC>(qb, filters, " OR ", "FALSE"),
+            Filter::Cond(cond) => cond.push_sql::<MC>(qb),
+        }
+    }
+}
+
+fn push_group<MC: DbBmc>(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    filters: &[Filter],
+    separator: &str,
+    empty: &str,
+) {
+    if filters.is_empty() {
+        qb.push(empty);
+        return;
+    }
+
+    qb.push("(");
+    for (i, filter) in filters.iter().enumerate() {
+        if i > 0 {
+            qb.push(separator);
+        }
+        filter.push_sql::<MC>(qb);
+    }
+    qb.push(")");
+}
+
+impl FilterCond {
+    fn validate(&self, v: &mut Validator, field_names: &[&str]) {
+        v.one_of("filters", &self.field, field_names);
+
+        let value_ok = match self.op {
+            FilterOp::Eq | FilterOp::Ne | FilterOp::Gt | FilterOp::Lt => is_scalar(&self.value),
+            FilterOp::In => {
+                matches!(&self.value, Value::Array(values) if values.iter().all(is_scalar))
+            }
+            FilterOp::Contains | FilterOp::StartsWith => self.value.is_string(),
+            FilterOp::Null => self.value.is_boolean(),
+        };
+
+        if !value_ok {
+            v.invalid(
+                "filters",
+                "filter_value",
+                format!("value of '{}' not valid for op {:?}", self.field, self.op),
+            );
+        }
+    }
+
+    /// A condition on a `DbBmc::RELATED_FIELDS` field matches the entities
+    /// with at least one related row matching it.
+    fn push_sql<MC: DbBmc>(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        let related = MC::RELATED_FIELDS
+            .iter()
+            .find(|(field, ..)| *field == self.field);
+
+        match related {
+            Some((field, table, entity_column)) => {
+                qb.push(format!(
+                    "EXISTS (SELECT 1 FROM \"{table}\" WHERE \"{table}\".\"{entity_column}\" = \"{}\".\"id\" AND ",
+                    MC::TABLE
+                ));
+                self.push_cond_sql::<MC>(qb, &format!("\"{table}\".\"{field}\""));
+                qb.push(")");
+            }
+            None => self.push_cond_sql::<MC>(qb, &format!("\"{}\"", self.field)),
+        }
+    }
+
+    fn push_cond_sql<MC: DbBmc>(&self, qb: &mut QueryBuilder<'_, Postgres>, column: &str) {
+        let sql_type = column_type(MC::COLUMN_TYPES, &self.field);
+
+        match self.op {
+            FilterOp::Eq => push_cmp(qb, column, " = ", &self.value, sql_type),
+            FilterOp::Ne => push_cmp(qb, column, " <> ", &self.value, sql_type),
+            FilterOp::Gt => push_cmp(qb, column, " > ", &self.value, sql_type),
+            FilterOp::Lt => push_cmp(qb, column, " < ", &self.value, sql_type),
+            FilterOp::In => {
+                let values = self.value.as_array().map(Vec::as_slice).unwrap_or_default();
+                if values.is_empty() {
+                    qb.push("FALSE");
+                    return;
+                }
+
+                qb.push(column).push(" IN (");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        qb.push(", ");
+                    }
+                    push_value(qb, value, sql_type);
+                }
+                qb.push(")");
+            }
+            FilterOp::Contains | FilterOp::StartsWith => {
+                let value = escape_like(self.value.as_str().unwrap_or_default());
+                let pattern = match self.op {
+                    FilterOp::Contains => format!("%{value}%"),
+                    _ => format!("{value}%"),
+                };
+                qb.push(column).push("::text LIKE ").push_bind(pattern);
+            }
+            FilterOp::Null => {
+                let is_null = self.value.as_bool().unwrap_or(true);
+                qb.push(column)
+                    .push(if is_null { " IS NULL" } else { " IS NOT NULL" });
+            }
+        }
+    }
+}
+
+fn push_cmp(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    column: &str,
+    op: &str,
+    value: &Value,
+    sql_type: Option<&str>,
+) {
+    qb.push(column).push(op);
+    push_value(qb, value, sql_type);
+}
+
+/// SQL type of the `field` in `column_types`, if its values must be cast.
+pub(in crate::model) fn column_type<'a>(
+    column_types: &[(&str, &'a str)],
+    field: &str,
+) -> Option<&'a str> {
+    column_types
+        .iter()
+        .find(|(column, _)| *column == field)
+        .map(|(_, sql_type)| *sql_type)
+}
+
+/// Binds a json scalar, with the SQL type of its json type, cast to the
+/// `sql_type` when given.
+pub(in crate::model) fn push_value(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    value: &Value,
+    sql_type: Option<&str>,
+) {
+    if let Some(sql_type) = sql_type {
+        qb.push("CAST(");
+        push_value(qb, value, None);
+        qb.push(format!(" AS {sql_type})"));
+        return;
+    }
+
+    match value {
+        Value::Bool(value) => qb.push_bind(*value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => qb.push_bind(value),
+            None => qb.push_bind(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(value) => qb.push_bind(value.clone()),
+        // Excluded by the validation.
+        Value::Null | Value::Array(_) | Value::Object(_) => qb.push("NULL"),
+    };
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(value, Value::Bool(_) | Value::Number(_) | Value::String(_))
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
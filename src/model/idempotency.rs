@@ -0,0 +1,226 @@
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::FromRow;
+
+use crate::config;
+use crate::ctx::Ctx;
+use crate::model::{Error, ModelManager, Result};
+
+const TABLE: &str = "idempotency_keys";
+
+/// Max number of expired keys purged by a claim.
+const PURGE_BATCH_SIZE: i64 = 100;
+
+/// Outcome of `IdempotencyBmc::claim`.
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// First use of the key, the call must be executed, then `complete`d,
+    /// in the transaction of the claim.
+    Claimed,
+    /// Key already completed, with its stored result.
+    Replay(Value),
+}
+
+#[derive(FromRow)]
+struct IdempotencyRecord {
+    method: String,
+    params: Option<Json<Value>>,
+    result: Option<Json<Value>>,
+}
+
+/// Idempotency keys, per user and workspace, so a call retried with the
+/// same key gets the stored result instead of being executed again.
+///
+/// Keys expire after `IDEMPOTENCY_TTL_SEC`.
+pub struct IdempotencyBmc;
+
+impl IdempotencyBmc {
+    /// Claims `key` for the call of `method` with `params`.
+    ///
+    /// Fails if the key is in use for a different call, or for the same
+    /// call still in progress.
+    pub async fn claim(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        key: &str,
+        method: &str,
+        params: Option<&Value>,
+    ) -> Result<IdempotencyClaim> {
+        let mut db = mm.db(ctx).await?;
+        let (user_id, workspace_id) = (ctx.user_id(), ctx.workspace_id());
+
+        // Expired keys of all the users are purged, a batch per claim,
+        // skipping the rows other claims are purging.
+        sqlx::query(&format!(
+            "DELETE FROM {TABLE} WHERE ctid IN (
+                SELECT ctid FROM {TABLE} WHERE ctime < now() - make_interval(secs => $1)
+                LIMIT {PURGE_BATCH_SIZE} FOR UPDATE SKIP LOCKED
+             )"
+        ))
+        .bind(config().IDEMPOTENCY_TTL_SEC)
+        .execute(&mut *db)
+        .await?;
+
+        // The key itself, if expired, so it can be claimed again.
+        sqlx::query(&format!(
+            "DELETE FROM {TABLE}
+             WHERE user_id = $1 AND workspace_id IS NOT DISTINCT FROM $2 AND key = $3
+               AND ctime < now() - make_interval(secs => $4)"
+        ))
+        .bind(user_id)
+        .bind(workspace_id)
+        .bind(key)
+        .bind(config().IDEMPOTENCY_TTL_SEC)
+        .execute(&mut *db)
+        .await?;
+
+        let inserted = sqlx::query(&format!(
+            "INSERT INTO {TABLE} (user_id, workspace_id, key, method, params)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT DO NOTHING"
+        ))
+        .bind(user_id)
+        .bind(workspace_id)
+        .bind(key)
+        .bind(method)
+        .bind(params.map(Json))
+        .execute(&mut *db)
+        .await?
+        .rows_affected();
+
+        if inserted == 1 {
+            return Ok(IdempotencyClaim::Claimed);
+        }
+
+        let record: IdempotencyRecord = sqlx::query_as(&format!(
+            "SELECT method, params, result FROM {TABLE}
+             WHERE user_id = $1 AND workspace_id IS NOT DISTINCT FROM $2 AND key = $3"
+        ))
+        .bind(user_id)
+        .bind(workspace_id)
+        .bind(key)
+        .fetch_one(&mut *db)
+        .await?;
+
+        if record.method != method || record.params.map(|p| p.0).as_ref() != params {
+            return Err(Error::IdempotencyKeyReused {
+                key: key.to_string(),
+            });
+        }
+
+        match record.result {
+            Some(result) => Ok(IdempotencyClaim::Replay(result.0)),
+            None => Err(Error::IdempotencyKeyInProgress {
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    /// Stores the `result` of the call `key` was claimed for.
+    pub async fn complete(ctx: &Ctx, mm: &ModelManager, key: &str, result: &Value) -> Result<()> {
+        let mut db = mm.db(ctx).await?;
+
+        sqlx::query(&format!(
+            "UPDATE {TABLE} SET result = $4
+             WHERE user_id = $1 AND workspace_id IS NOT DISTINCT FROM $2 AND key = $3"
+        ))
+        .bind(ctx.user_id())
+        .bind(ctx.workspace_id())
+        .bind(key)
+        .bind(Json(result))
+        .execute(&mut *db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_claim_replay_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_key = "test_claim_replay_ok-key";
+        let fx_params = json!({"data": {"title": "t1"}});
+
+        let claim =
+            IdempotencyBmc::claim(&ctx, &mm, fx_key, "create_task", Some(&fx_params)).await?;
+        assert!(matches!(claim, IdempotencyClaim::Claimed));
+
+        let res = IdempotencyBmc::claim(&ctx, &mm, fx_key, "create_task", Some(&fx_params)).await;
+        assert!(
+            matches!(&res, Err(Error::IdempotencyKeyInProgress { .. })),
+            "Should have matched Err(IdempotencyKeyInProgress) but was `{res:?}`"
+        );
+
+        IdempotencyBmc::complete(&ctx, &mm, fx_key, &json!({"id": 1})).await?;
+
+        let claim =
+            IdempotencyBmc::claim(&ctx, &mm, fx_key, "create_task", Some(&fx_params)).await?;
+        assert!(matches!(claim, IdempotencyClaim::Replay(result) if result == json!({"id": 1})));
+
+        let res = IdempotencyBmc::claim(&ctx, &mm, fx_key, "delete_task", Some(&fx_params)).await;
+        assert!(
+            matches!(&res, Err(Error::IdempotencyKeyReused { .. })),
+            "Should have matched Err(IdempotencyKeyReused) but was `{res:?}`"
+        );
+
+        // Same key in a workspace.
+        let ctx_workspace = ctx.clone().with_workspace_id(Some(1000));
+        let claim =
+            IdempotencyBmc::claim(&ctx_workspace, &mm, fx_key, "delete_task", Some(&fx_params))
+                .await?;
+        assert!(matches!(claim, IdempotencyClaim::Claimed));
+
+        sqlx::query("DELETE FROM idempotency_keys WHERE key = $1")
+            .bind(fx_key)
+            .execute(&mut *mm.db(&ctx).await?)
+            .await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_claim_purge_expired_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_key = "test_claim_purge_expired_ok-key";
+
+        // Expired key of another user.
+        sqlx::query(
+            "INSERT INTO idempotency_keys (user_id, workspace_id, key, method, ctime)
+             VALUES (1000, 1000, $1, 'create_task', now() - make_interval(secs => $2 + 1))",
+        )
+        .bind(fx_key)
+        .bind(config().IDEMPOTENCY_TTL_SEC)
+        .execute(&mut *mm.db(&ctx).await?)
+        .await?;
+
+        let claim = IdempotencyBmc::claim(&ctx, &mm, fx_key, "create_task", None).await?;
+        assert!(matches!(claim, IdempotencyClaim::Claimed));
+
+        let (count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM idempotency_keys WHERE key = $1")
+                .bind(fx_key)
+                .fetch_one(&mut *mm.db(&ctx).await?)
+                .await?;
+        assert_eq!(count, 1, "only the claim of root should remain");
+
+        sqlx::query("DELETE FROM idempotency_keys WHERE key = $1")
+            .bind(fx_key)
+            .execute(&mut *mm.db(&ctx).await?)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use crate::ctx::Ctx;
+use crate::model::{
+    base::{self, DbBmc},
+    event::{EventScope, ModelEventKind},
+    filter::Filter,
+    list_options::{ListOptions, Page},
+    task::TaskBmc,
+    validation::{Validate, Validator},
+    Error, ModelManager, Result,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlb::{Fields, HasFields};
+use sqlx::{FromRow, Row};
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, Fields, FromRow, Serialize, JsonSchema)]
+pub struct Label {
+    pub id: i64,
+    pub workspace_id: i64,
+    pub name: String,
+
+    pub cid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub mtime: OffsetDateTime,
+}
+
+#[derive(Fields, Deserialize, JsonSchema)]
+pub struct LabelForCreate {
+    pub name: String,
+}
+
+#[derive(Fields, Deserialize, JsonSchema)]
+pub struct LabelForUpdate {
+    pub name: Option<String>,
+}
+
+const NAME_MAX_CHARS: usize = 255;
+
+impl Validate for LabelForCreate {
+    fn validate(&self, v: &mut Validator) {
+        v.not_blank("name", &self.name)
+            .max_chars("name", &self.name, NAME_MAX_CHARS);
+    }
+}
+
+impl Validate for LabelForUpdate {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(name) = &self.name {
+            v.not_blank("name", name)
+                .max_chars("name", name, NAME_MAX_CHARS);
+        }
+    }
+}
+
+pub struct LabelBmc;
+
+impl DbBmc for LabelBmc {
+    const TABLE: &'static str = "labels";
+    const WORKSPACED: bool = true;
+}
+
+/// Join table of the task labels, see `TaskBmc::RELATED_FIELDS`.
+pub(in crate::model) const TASK_LABELS_TABLE: &str = "task_labels";
+
+impl LabelBmc {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, label_c: LabelForCreate) -> Result<i64> {
+        base::create::<Self, _>(ctx, mm, label_c).await
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Label> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<Filter>,
+        list_options: ListOptions,
+    ) -> Result<Page<Label>> {
+        base::list::<Self, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        label_u: LabelForUpdate,
+    ) -> Result<()> {
+        base::update::<Self, _>(ctx, mm, id, label_u, None).await
+    }
+
+    /// Deletes the label, detached from its tasks, with an `Updated` event
+    /// for each of them.
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        mm.transaction(|mm| async move {
+            let label = Self::get(ctx, &mm, id).await?;
+
+            // Tasks of all the owners of the workspace.
+            let root_ctx = Ctx::root_ctx().with_workspace_id(Some(label.workspace_id));
+            let mut db = mm.db(&root_ctx).await?;
+            let tasks: Vec<(i64, i64, i64)> = sqlx::query_as(&format!(
+                "SELECT t.id, t.owner_id, t.workspace_id FROM {TASK_LABELS_TABLE} tl \
+                 JOIN {} t ON t.id = tl.task_id WHERE tl.label_id = $1",
+                TaskBmc::TABLE
+            ))
+            .bind(id)
+            .fetch_all(&mut *db)
+            .await?;
+            drop(db);
+
+            base::delete::<Self>(ctx, &mm, id).await?;
+
+            for (task_id, owner_id, workspace_id) in tasks {
+                let scope = EventScope {
+                    owner_id: Some(owner_id),
+                    workspace_id: Some(workspace_id),
+                };
+                mm.publish_event(TaskBmc::TABLE, task_id, scope, ModelEventKind::Updated);
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Attaches the label to the task, of the same workspace (no-op if
+    /// already attached).
+    pub async fn attach(ctx: &Ctx, mm: &ModelManager, task_id: i64, label_id: i64) -> Result<()> {
+        let task = TaskBmc::get(ctx, mm, task_id).await?;
+        let label = Self::get(ctx, mm, label_id).await?;
+        // Only possible for the root ctx without workspace.
+        if label.workspace_id != task.workspace_id {
+            return Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id: label_id,
+            });
+        }
+
+        let mut db = mm.db(ctx).await?;
+        sqlx::query(&format!(
+            "INSERT INTO {TASK_LABELS_TABLE} (task_id, label_id, cid, ctime) \
+             VALUES ($1, $2, $3, now()) ON CONFLICT DO NOTHING"
+        ))
+        .bind(task_id)
+        .bind(label_id)
+        .bind(ctx.user_id())
+        .execute(&mut *db)
+        .await?;
+        drop(db);
+
+        let scope = EventScope {
+            owner_id: Some(task.owner_id),
+            workspace_id: Some(task.workspace_id),
+        };
+        mm.publish_event(TaskBmc::TABLE, task_id, scope, ModelEventKind::Updated);
+
+        Ok(())
+    }
+
+    /// Detaches the label from the task (no-op if not attached).
+    pub async fn detach(ctx: &Ctx, mm: &ModelManager, task_id: i64, label_id: i64) -> Result<()> {
+        let task = TaskBmc::get(ctx, mm, task_id).await?;
+
+        let mut db = mm.db(ctx).await?;
+        sqlx::query(&format!(
+            "DELETE FROM {TASK_LABELS_TABLE} WHERE task_id = $1 AND label_id = $2"
+        ))
+        .bind(task_id)
+        .bind(label_id)
+        .execute(&mut *db)
+        .await?;
+        drop(db);
+
+        let scope = EventScope {
+            owner_id: Some(task.owner_id),
+            workspace_id: Some(task.workspace_id),
+        };
+        mm.publish_event(TaskBmc::TABLE, task_id, scope, ModelEventKind::Updated);
+
+        Ok(())
+    }
+
+    /// Labels of each of the tasks of `task_ids` (already accessed through
+    /// the ctx), by name, with one query.
+    pub async fn list_by_task_ids(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        task_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<Label>>> {
+        let columns = Label::field_names()
+            .iter()
+            .map(|field| format!("l.\"{field}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "SELECT tl.task_id, {columns} FROM {TASK_LABELS_TABLE} tl \
+             JOIN {} l ON l.id = tl.label_id \
+             WHERE tl.task_id = ANY($1) ORDER BY l.name, l.id",
+            Self::TABLE
+        );
+
+        let mut db = mm.db(ctx).await?;
+        let rows = sqlx::query(&sql).bind(task_ids).fetch_all(&mut *db).await?;
+
+        let mut labels: HashMap<i64, Vec<Label>> = HashMap::new();
+        for row in rows {
+            let task_id: i64 = row.try_get("task_id")?;
+            labels
+                .entry(task_id)
+                .or_default()
+                .push(Label::from_row(&row)?);
+        }
+
+        Ok(labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+
+    use super::*;
+    use anyhow::Result;
+    use serde_json::json;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_attach_list_by_labels_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let fx_tasks = _dev_utils::seed_test(
+            &ctx,
+            &mm,
+            &[
+                "test_attach_list_by_labels_ok 01",
+                "test_attach_list_by_labels_ok 02",
+                "test_attach_list_by_labels_ok 03",
+            ],
+        )
+        .await?;
+        let mut label_ids = Vec::new();
+        for name in ["urgent", "bug"] {
+            let label_c = LabelForCreate {
+                name: name.to_string(),
+            };
+            label_ids.push(LabelBmc::create(&ctx, &mm, label_c).await?);
+        }
+        let (urgent_id, bug_id) = (label_ids[0], label_ids[1]);
+
+        LabelBmc::attach(&ctx, &mm, fx_tasks[0].id, urgent_id).await?;
+        LabelBmc::attach(&ctx, &mm, fx_tasks[0].id, bug_id).await?;
+        // Already attached.
+        LabelBmc::attach(&ctx, &mm, fx_tasks[0].id, bug_id).await?;
+        LabelBmc::attach(&ctx, &mm, fx_tasks[1].id, bug_id).await?;
+
+        let task_ids: Vec<i64> = fx_tasks.iter().map(|task| task.id).collect();
+        let labels = LabelBmc::list_by_task_ids(&ctx, &mm, &task_ids).await?;
+        let names: Vec<&str> = labels[&fx_tasks[0].id]
+            .iter()
+            .map(|label| label.name.as_str())
+            .collect();
+        assert_eq!(names, ["bug", "urgent"]);
+        assert!(!labels.contains_key(&fx_tasks[2].id));
+
+        // Tasks with all the labels.
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(json!([
+                {"field": "label_id", "op": "eq", "value": urgent_id},
+                {"field": "label_id", "op": "eq", "value": bug_id},
+            ]))?,
+            Default::default(),
+        )
+        .await?;
+        let ids: Vec<i64> = page.items.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [fx_tasks[0].id]);
+
+        // Tasks with any of the labels.
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(json!([
+                {"field": "label_id", "op": "in", "value": [urgent_id, bug_id]},
+            ]))?,
+            Default::default(),
+        )
+        .await?;
+        assert_eq!(page.total, 2);
+
+        LabelBmc::detach(&ctx, &mm, fx_tasks[1].id, bug_id).await?;
+        let mut events = mm.subscribe_events();
+        LabelBmc::delete(&ctx, &mm, urgent_id).await?;
+        let event = events.try_recv()?;
+        assert_eq!(event.entity, "labels");
+        let event = events.try_recv()?;
+        assert_eq!((event.entity, event.entity_id), ("tasks", fx_tasks[0].id));
+        assert!(matches!(event.kind, ModelEventKind::Updated));
+        assert!(events.try_recv().is_err(), "one event per detached task");
+        let labels = LabelBmc::list_by_task_ids(&ctx, &mm, &task_ids).await?;
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[&fx_tasks[0].id].len(), 1);
+
+        LabelBmc::delete(&ctx, &mm, bug_id).await?;
+        for task in fx_tasks {
+            TaskBmc::purge(&ctx, &mm, task.id).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::model::validation::Validator;
+
+/// Page size when `ListOptions.limit` is not set.
+pub const LIST_LIMIT_DEFAULT: u32 = 100;
+
+/// Max page size, larger limits are capped to it.
+pub const LIST_LIMIT_MAX: u32 = 1000;
+
+/// Paging and ordering of a `base::list`.
+///
+/// Without `order_bys`, entities are ordered by `id`.
+/// Pages are selected either by `offset`, or by the `cursor` of the
+/// previous page (`Page.next_cursor`), with the same `order_bys`.
+/// Soft deleted entities are only listed with `include_deleted`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ListOptions {
+    pub limit: Option<u32>,
+    pub offset: Option<u64>,
+    pub cursor: Option<String>,
+    pub order_bys: Option<Vec<OrderBy>>,
+    pub include_deleted: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct OrderBy {
+    pub field: String,
+    #[serde(default)]
+    pub direction: OrderDirection,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// One page of a `base::list`, with the `total` count of entities.
+///
+/// `next_cursor` is set when there are more entities after this page.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Page<E> {
+    pub items: Vec<E>,
+    pub total: i64,
+    pub limit: u32,
+    pub offset: u64,
+    pub next_cursor: Option<String>,
+}
+
+impl ListOptions {
+    /// Page size, capped to `LIST_LIMIT_MAX`.
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(LIST_LIMIT_DEFAULT).min(LIST_LIMIT_MAX)
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset.unwrap_or(0)
+    }
+
+    /// Order by fields and directions, with `id` last so the order is
+    /// total and the pages are stable.
+    pub fn sort_key(&self) -> Vec<(&str, OrderDirection)> {
+        let mut sort_key: Vec<(&str, OrderDirection)> = self
+            .order_bys
+            .iter()
+            .flatten()
+            .map(|order_by| (order_by.field.as_str(), order_by.direction))
+            .collect();
+
+        if !sort_key.iter().any(|(field, _)| *field == "id") {
+            sort_key.push(("id", OrderDirection::Asc));
+        }
+
+        sort_key
+    }
+
+    /// SQL `ORDER BY` list of the `sort_key`.
+    ///
+    /// Must be validated first, the field names are not escaped.
+    pub(in crate::model) fn order_by_sql(&self) -> String {
+        self.sort_key()
+            .into_iter()
+            .map(|(field, direction)| match direction {
+                OrderDirection::Asc => format!("\"{field}\""),
+                OrderDirection::Desc => format!("\"{field}\" DESC"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Checks the order by fields are among the entity `field_names`.
+    pub fn validate(&self, v: &mut Validator, field_names: &[&str]) {
+        for order_by in self.order_bys.iter().flatten() {
+            v.one_of("order_bys", &order_by.field, field_names);
+        }
+
+        // Bound as a BIGINT.
+        if self.offset() > i64::MAX as u64 {
+            v.invalid(
+                "offset",
+                "range",
+                format!("must be between 0 and {}", i64::MAX),
+            );
+        }
+
+        if self.cursor.is_some() && self.offset.is_some() {
+            v.invalid(
+                "cursor",
+                "exclusive",
+                "cursor and offset cannot be both set".to_string(),
+            );
+        }
+    }
+}
@@ -1,25 +1,165 @@
 mod base;
+pub mod comment;
+mod cursor;
 mod error;
+pub mod event;
+pub mod filter;
+pub mod idempotency;
+pub mod label;
+pub mod list_options;
+pub mod project;
 mod store;
 pub mod task;
 pub mod user;
+pub mod validation;
+pub mod workspace;
 
 pub use self::error::{Error, Result};
-use store::{new_db_pool, Db};
+use crate::ctx::Ctx;
+use event::{EventHub, EventScope, ModelEvent, ModelEventKind};
+use std::future::Future;
+use std::sync::Arc;
+pub use store::DB_MAX_CONNECTIONS;
+use store::{new_db_pool, set_app_ctx, Db, DbConn, DbTxn};
+use tokio::sync::{broadcast, Mutex};
 
 #[derive(Clone)]
 pub struct ModelManager {
     db: Db,
+    events: EventHub,
+    /// Set for a transactional ModelManager, see `transaction`.
+    txn: Option<TxnState>,
+}
+
+/// Entity, entity id, scope and kind of a model event.
+type PendingEvent = (&'static str, i64, EventScope, ModelEventKind);
+
+#[derive(Clone)]
+struct TxnState {
+    /// Taken when the transaction ends.
+    txn: Arc<Mutex<Option<DbTxn>>>,
+    /// Events published once the transaction is committed.
+    pending_events: Arc<std::sync::Mutex<Vec<PendingEvent>>>,
 }
 
 impl ModelManager {
     pub async fn new() -> Result<Self> {
         let db = new_db_pool().await?;
 
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            events: EventHub::new(),
+            txn: None,
+        })
+    }
+
+    /// Runs `f` with a transactional ModelManager, so all the Bmc calls
+    /// made with it run in one database transaction.
+    ///
+    /// The transaction is committed when `f` returns `Ok`, and rolled back
+    /// when it returns `Err`. The model events are only published on
+    /// commit. Called on a transactional ModelManager, `f` joins the
+    /// current transaction.
+    pub async fn transaction<F, Fut, T, E>(&self, f: F) -> core::result::Result<T, E>
+    where
+        F: FnOnce(ModelManager) -> Fut,
+        Fut: Future<Output = core::result::Result<T, E>>,
+        E: From<Error>,
+    {
+        if self.txn.is_some() {
+            return f(self.clone()).await;
+        }
+
+        let txn = self.db.begin().await.map_err(Error::from)?;
+        let txn_state = TxnState {
+            txn: Arc::new(Mutex::new(Some(txn))),
+            pending_events: Arc::default(),
+        };
+        let mm = ModelManager {
+            txn: Some(txn_state.clone()),
+            ..self.clone()
+        };
+
+        let res = f(mm).await;
+
+        let txn = txn_state.txn.lock().await.take().ok_or(Error::TxnEnded)?;
+        match res {
+            Ok(value) => {
+                txn.commit().await.map_err(Error::from)?;
+
+                let pending_events = std::mem::take(&mut *txn_state.pending_events.lock().unwrap());
+                for (entity, entity_id, scope, kind) in pending_events {
+                    self.events.publish(entity, entity_id, scope, kind);
+                }
+
+                Ok(value)
+            }
+            Err(ex) => {
+                // The rollback error, if any, is less relevant than `ex`.
+                let _ = txn.rollback().await;
+                Err(ex)
+            }
+        }
+    }
+
+    /// Receiver of the events of the entities created, updated or deleted
+    /// through this ModelManager (and its clones).
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ModelEvent> {
+        self.events.subscribe()
+    }
+
+    /// Like `subscribe_events`, also returning the recent events after
+    /// `last_id` still buffered, to resume a subscription.
+    pub fn subscribe_events_since(
+        &self,
+        last_id: Option<u64>,
+    ) -> (Vec<ModelEvent>, broadcast::Receiver<ModelEvent>) {
+        self.events.subscribe_since(last_id)
+    }
+
+    /// Connection to run the queries of `ctx` on, the transaction for a
+    /// transactional ModelManager.
+    ///
+    /// The ctx user and workspace are set on it for the row level security
+    /// policies, with `SET LOCAL` in a transaction. A pool connection keeps
+    /// them for its session, until released to the pool.
+    ///
+    /// In a transaction, it is exclusive until dropped, so must not be held
+    /// while calling another Bmc function.
+    pub(in crate::model) async fn db(&self, ctx: &Ctx) -> Result<DbConn<'_>> {
+        let mut db = match &self.txn {
+            Some(txn_state) => {
+                let txn = txn_state.txn.lock().await;
+                if txn.is_none() {
+                    return Err(Error::TxnEnded);
+                }
+                DbConn::Txn(txn)
+            }
+            None => DbConn::Pool(Box::new(self.db.acquire().await?)),
+        };
+
+        let local = matches!(db, DbConn::Txn(_));
+        set_app_ctx(&mut db, ctx.user_id(), ctx.workspace_id(), local).await?;
+
+        Ok(db)
     }
 
-    pub(in crate::model) fn db(&self) -> &Db {
-        &self.db
+    /// Publishes a model event, or keeps it until the commit of the
+    /// transaction.
+    pub(in crate::model) fn publish_event(
+        &self,
+        entity: &'static str,
+        entity_id: i64,
+        scope: EventScope,
+        kind: ModelEventKind,
+    ) {
+        match &self.txn {
+            Some(txn_state) => txn_state
+                .pending_events
+                .lock()
+                .unwrap()
+                .push((entity, entity_id, scope, kind)),
+            None => self.events.publish(entity, entity_id, scope, kind),
+        }
     }
 }
@@ -0,0 +1,234 @@
+use crate::ctx::Ctx;
+use crate::model::{
+    base::{self, DbBmc},
+    filter::Filter,
+    list_options::{ListOptions, Page},
+    task::TaskBmc,
+    validation::{Validate, Validator},
+    Error, ModelManager, Result,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlb::Fields;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, Fields, FromRow, Serialize, JsonSchema)]
+pub struct Project {
+    pub id: i64,
+    pub workspace_id: i64,
+    pub name: String,
+
+    pub cid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub mtime: OffsetDateTime,
+}
+
+#[derive(Fields, Deserialize, JsonSchema)]
+pub struct ProjectForCreate {
+    pub name: String,
+}
+
+#[derive(Fields, Deserialize, JsonSchema)]
+pub struct ProjectForUpdate {
+    pub name: Option<String>,
+}
+
+const NAME_MAX_CHARS: usize = 255;
+
+impl Validate for ProjectForCreate {
+    fn validate(&self, v: &mut Validator) {
+        v.not_blank("name", &self.name)
+            .max_chars("name", &self.name, NAME_MAX_CHARS);
+    }
+}
+
+impl Validate for ProjectForUpdate {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(name) = &self.name {
+            v.not_blank("name", name)
+                .max_chars("name", name, NAME_MAX_CHARS);
+        }
+    }
+}
+
+pub struct ProjectBmc;
+
+impl DbBmc for ProjectBmc {
+    const TABLE: &'static str = "projects";
+    const WORKSPACED: bool = true;
+}
+
+impl ProjectBmc {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, project_c: ProjectForCreate) -> Result<i64> {
+        base::create::<Self, _>(ctx, mm, project_c).await
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Project> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<Filter>,
+        list_options: ListOptions,
+    ) -> Result<Page<Project>> {
+        base::list::<Self, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        project_u: ProjectForUpdate,
+    ) -> Result<()> {
+        base::update::<Self, _>(ctx, mm, id, project_u, None).await
+    }
+
+    /// Deletes the project, refused with `EntityInUse` while it has tasks
+    /// (of any owner). Its soft deleted tasks are detached from it.
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        mm.transaction(|mm| async move {
+            // Access check, and lock against new tasks of the project.
+            Self::get(ctx, &mm, id).await?;
+            let root_ctx = Ctx::root_ctx().with_workspace_id(ctx.workspace_id());
+            let mut db = mm.db(&root_ctx).await?;
+            sqlx::query(&format!(
+                "SELECT id FROM {} WHERE id = $1 FOR UPDATE",
+                Self::TABLE
+            ))
+            .bind(id)
+            .execute(&mut *db)
+            .await?;
+
+            let (task_count,): (i64,) = sqlx::query_as(&format!(
+                "SELECT count(*) FROM {} WHERE project_id = $1 AND deleted_at IS NULL",
+                TaskBmc::TABLE
+            ))
+            .bind(id)
+            .fetch_one(&mut *db)
+            .await?;
+            drop(db);
+
+            if task_count > 0 {
+                return Err(Error::EntityInUse {
+                    entity: Self::TABLE,
+                    id,
+                    by: TaskBmc::TABLE,
+                });
+            }
+
+            base::delete::<Self>(ctx, &mm, id).await
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+    use crate::model::task::TaskForCreate;
+    use crate::model::workspace::{WorkspaceBmc, WorkspaceForCreate};
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_delete_err_in_use() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let project_id = ProjectBmc::create(
+            &ctx,
+            &mm,
+            ProjectForCreate {
+                name: "test_delete_err_in_use project".to_string(),
+            },
+        )
+        .await?;
+        let task_id = TaskBmc::create(
+            &ctx,
+            &mm,
+            TaskForCreate {
+                title: "test_delete_err_in_use task".to_string(),
+                project_id: Some(project_id),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let res = ProjectBmc::delete(&ctx, &mm, project_id).await;
+        assert!(
+            matches!(res, Err(Error::EntityInUse { id, .. }) if id == project_id),
+            "EntityInUse not matching: {res:?}"
+        );
+
+        // Soft deleted tasks are detached.
+        TaskBmc::delete(&ctx, &mm, task_id).await?;
+        ProjectBmc::delete(&ctx, &mm, project_id).await?;
+        let task = TaskBmc::get_include_deleted(&ctx, &mm, task_id).await?;
+        assert_eq!(task.project_id, None);
+
+        TaskBmc::purge(&ctx, &mm, task_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_task_project_of_other_workspace() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let workspace_id = WorkspaceBmc::create(
+            &ctx,
+            &mm,
+            WorkspaceForCreate {
+                name: "test_task_project_of_other_workspace".to_string(),
+            },
+        )
+        .await?;
+        let project_id = ProjectBmc::create(
+            &ctx.clone().with_workspace_id(Some(workspace_id)),
+            &mm,
+            ProjectForCreate {
+                name: "test_task_project_of_other_workspace".to_string(),
+            },
+        )
+        .await?;
+
+        let res = TaskBmc::create(
+            &ctx,
+            &mm,
+            TaskForCreate {
+                title: "test_task_project_of_other_workspace".to_string(),
+                project_id: Some(project_id),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "project_id"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        let res = ProjectBmc::get(&ctx, &mm, project_id).await;
+        assert!(
+            matches!(res, Err(Error::EntityNotFound { .. })),
+            "EntityNotFound not matching: {res:?}"
+        );
+
+        sqlx::query("DELETE FROM workspaces WHERE id = $1")
+            .bind(workspace_id)
+            .execute(&mut *mm.db(&Ctx::root_ctx()).await?)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -2,15 +2,90 @@ mod error;
 
 pub use self::error::{Error, Result};
 
+use std::ops::{Deref, DerefMut};
+
 use crate::config;
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use sqlx::pool::PoolConnection;
+use sqlx::{postgres::PgPoolOptions, Executor, PgConnection, Pool, Postgres, Transaction};
+use tokio::sync::MutexGuard;
 
 pub type Db = Pool<Postgres>;
 
+pub type DbTxn = Transaction<'static, Postgres>;
+
+/// Settings read by the row level security policies, the user id (`0` for
+/// the root ctx) and the workspace id of the ctx the queries run for.
+const APP_USER_ID_SETTING: &str = "app.user_id";
+const APP_WORKSPACE_ID_SETTING: &str = "app.workspace_id";
+
+/// Size of the pool, the max number of queries run at once.
+pub const DB_MAX_CONNECTIONS: u32 = 5;
+
 pub async fn new_db_pool() -> Result<Db> {
     PgPoolOptions::new()
-        .max_connections(5)
+        .max_connections(DB_MAX_CONNECTIONS)
+        // So an idle connection has no user, see `set_app_ctx`.
+        .after_release(|conn, _| {
+            Box::pin(async move {
+                conn.execute(
+                    format!("RESET {APP_USER_ID_SETTING}; RESET {APP_WORKSPACE_ID_SETTING}")
+                        .as_str(),
+                )
+                .await?;
+                Ok(true)
+            })
+        })
         .connect(&config().DB_URL)
         .await
         .map_err(|e| Error::FaileToCreatePool(e.to_string()))
 }
+
+/// Sets the user and workspace the row level security policies let the next
+/// queries of `conn` access, until the end of the transaction when `local`
+/// (`SET LOCAL`), or of the session otherwise.
+pub async fn set_app_ctx(
+    conn: &mut PgConnection,
+    user_id: i64,
+    workspace_id: Option<i64>,
+    local: bool,
+) -> sqlx::Result<()> {
+    sqlx::query("SELECT set_config($1, $2, $5), set_config($3, $4, $5)")
+        .bind(APP_USER_ID_SETTING)
+        .bind(user_id.to_string())
+        .bind(APP_WORKSPACE_ID_SETTING)
+        // Empty for none, the policies read it as NULL.
+        .bind(workspace_id.map(|id| id.to_string()).unwrap_or_default())
+        .bind(local)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Connection the Bmc queries run on, a pool connection, or the
+/// transaction of a transactional `ModelManager`.
+pub enum DbConn<'a> {
+    Pool(Box<PoolConnection<Postgres>>),
+    /// Checked to be `Some` on creation.
+    Txn(MutexGuard<'a, Option<DbTxn>>),
+}
+
+impl Deref for DbConn<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        match self {
+            DbConn::Pool(conn) => conn,
+            DbConn::Txn(txn) => txn.as_ref().expect("DbConn::Txn should be Some"),
+        }
+    }
+}
+
+impl DerefMut for DbConn<'_> {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        match self {
+            DbConn::Pool(conn) => conn,
+            DbConn::Txn(txn) => txn.as_mut().expect("DbConn::Txn should be Some"),
+        }
+    }
+}
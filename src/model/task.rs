@@ -1,32 +1,194 @@
 use crate::ctx::Ctx;
 use crate::model::{
-    base::{self, DbBmc},
-    Error, ModelManager, Result,
+    base::{self, DbBmc, Reference},
+    filter::Filter,
+    label::{Label, LabelBmc, TASK_LABELS_TABLE},
+    list_options::{ListOptions, Page},
+    project::ProjectBmc,
+    validation::{Validate, Validator},
+    ModelManager, Result,
 };
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlb::Fields;
 use sqlx::FromRow;
+use time::OffsetDateTime;
 
-#[derive(Clone, Debug, Fields, FromRow, Serialize)]
+#[derive(Clone, Debug, Fields, FromRow, Serialize, JsonSchema)]
 pub struct Task {
     pub id: i64,
+    pub workspace_id: i64,
+    pub project_id: Option<i64>,
+    /// Parent task, `None` for a root task.
+    pub parent_id: Option<i64>,
     pub title: String,
+    pub description: Option<String>,
+    pub status: TaskStatus,
+    #[serde(with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub due_at: Option<OffsetDateTime>,
+    pub priority: i16,
+    pub owner_id: i64,
+    pub version: i64,
+
+    pub cid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub mtime: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339::option")]
+    #[schemars(with = "Option<String>")]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+/// Task with its labels, as returned by the rpcs.
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+pub struct TaskWithLabels {
+    #[serde(flatten)]
+    pub task: Task,
+    pub labels: Vec<Label>,
+}
+
+/// Rollup of the subtasks of a task, at any depth, soft deleted ones
+/// excluded.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaskProgress {
+    pub done: i64,
+    pub total: i64,
 }
 
-#[derive(Fields, Deserialize)]
+/// Status of a task, ordered from `todo` to `done`.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "task_status", rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Todo,
+    InProgress,
+    Done,
+}
+
+sqlb::bindable!(TaskStatus);
+
+/// Without `status` and `priority`, the task is `todo` and of priority 0.
+#[derive(Default, Fields, Deserialize, JsonSchema)]
 pub struct TaskForCreate {
     pub title: String,
+    /// Project of the ctx workspace.
+    pub project_id: Option<i64>,
+    /// Parent task, of the ctx user and workspace.
+    pub parent_id: Option<i64>,
+    pub description: Option<String>,
+    pub status: Option<TaskStatus>,
+    #[serde(
+        default,
+        deserialize_with = "time::serde::rfc3339::option::deserialize"
+    )]
+    #[schemars(with = "Option<String>")]
+    pub due_at: Option<OffsetDateTime>,
+    /// 0 (none) to 3 (high).
+    pub priority: Option<i16>,
 }
 
-#[derive(Fields, Deserialize)]
+/// `project_id` moves the task to another project, a task cannot be
+/// detached from its project. A `null` `description` or `due_at` clears it. The
+/// parent is changed by `TaskBmc::move_to_parent`, which prevents cycles.
+#[derive(Default, Fields, Deserialize, JsonSchema)]
 pub struct TaskForUpdate {
     pub title: Option<String>,
+    pub project_id: Option<i64>,
+    #[serde(default, deserialize_with = "deserialize_clearable")]
+    #[schemars(with = "Option<String>")]
+    pub description: Option<Option<String>>,
+    pub status: Option<TaskStatus>,
+    #[serde(default, deserialize_with = "deserialize_clearable_time")]
+    #[schemars(with = "Option<String>")]
+    pub due_at: Option<Option<OffsetDateTime>>,
+    pub priority: Option<i16>,
+}
+
+/// Present value or `null` (clearing the column), the field being `None`
+/// only when absent.
+fn deserialize_clearable<'de, D, T>(
+    deserializer: D,
+) -> core::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// `deserialize_clearable` of an RFC 3339 value.
+fn deserialize_clearable_time<'de, D>(
+    deserializer: D,
+) -> core::result::Result<Option<Option<OffsetDateTime>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    time::serde::rfc3339::option::deserialize(deserializer).map(Some)
+}
+
+const TITLE_MAX_CHARS: usize = 255;
+const DESCRIPTION_MAX_CHARS: usize = 10_000;
+const PRIORITY_MAX: i64 = 3;
+
+impl Validate for TaskForCreate {
+    fn validate(&self, v: &mut Validator) {
+        v.not_blank("title", &self.title)
+            .max_chars("title", &self.title, TITLE_MAX_CHARS);
+        validate_details(v, self.description.as_deref(), self.priority);
+    }
+}
+
+impl Validate for TaskForUpdate {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(title) = &self.title {
+            v.not_blank("title", title)
+                .max_chars("title", title, TITLE_MAX_CHARS);
+        }
+        let description = self.description.as_ref().and_then(Option::as_deref);
+        validate_details(v, description, self.priority);
+    }
+}
+
+fn validate_details(v: &mut Validator, description: Option<&str>, priority: Option<i16>) {
+    if let Some(description) = description {
+        v.max_chars("description", description, DESCRIPTION_MAX_CHARS);
+    }
+    if let Some(priority) = priority {
+        v.range("priority", priority.into(), 0, PRIORITY_MAX);
+    }
 }
 
 pub struct TaskBmc;
 
 impl DbBmc for TaskBmc {
     const TABLE: &'static str = "tasks";
+    const SOFT_DELETE: bool = true;
+    const VERSIONED: bool = true;
+    const OWNED: bool = true;
+    const WORKSPACED: bool = true;
+    const REFERENCES: &'static [Reference] = &[
+        Reference::to::<ProjectBmc>("project_id", &["workspace_id"]),
+        Reference::to::<TaskBmc>("parent_id", &["workspace_id", "owner_id"]),
+    ];
+    const COLUMN_TYPES: &'static [(&'static str, &'static str)] = &[
+        ("status", "task_status"),
+        ("due_at", "timestamptz"),
+        ("deleted_at", "timestamptz"),
+        ("ctime", "timestamptz"),
+        ("mtime", "timestamptz"),
+    ];
+    const RELATED_FIELDS: &'static [(&'static str, &'static str, &'static str)] =
+        &[("label_id", TASK_LABELS_TABLE, "task_id")];
+    const TREE: bool = true;
 }
 
 impl TaskBmc {
@@ -35,57 +197,179 @@ impl TaskBmc {
     }
 
     pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Task> {
-        base::get::<Self, _>(ctx, &mm, id).await
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn get_include_deleted(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Task> {
+        base::get_include_deleted::<Self, _>(ctx, mm, id).await
     }
 
+    /// Soft deletes the task, its subtasks being moved under its parent.
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
-        base::delete::<Self>(ctx, &mm, id).await
+        base::delete::<Self>(ctx, mm, id).await
+    }
+
+    pub async fn restore(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::restore::<Self>(ctx, mm, id).await
     }
 
-    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<Task>> {
-        base::list::<Self, _>(ctx, &mm).await
+    pub async fn purge(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::purge::<Self>(ctx, mm, id).await
     }
 
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<Filter>,
+        list_options: ListOptions,
+    ) -> Result<Page<Task>> {
+        base::list::<Self, _>(ctx, mm, filters, list_options).await
+    }
+
+    /// Updates the task, if still at `version` when given.
     pub async fn update(
         ctx: &Ctx,
         mm: &ModelManager,
         id: i64,
         task_u: TaskForUpdate,
+        version: Option<i64>,
+    ) -> Result<()> {
+        let res = base::update::<Self, _>(ctx, mm, id, task_u, version).await;
+
+        base::with_conflict_current::<Self, Task>(ctx, mm, res).await
+    }
+
+    pub async fn get_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        ids: &[i64],
+        include_deleted: bool,
+    ) -> Result<Vec<Task>> {
+        base::get_many::<Self, _>(ctx, mm, ids, include_deleted).await
+    }
+
+    /// Creates the valid tasks, see `base::create_many`.
+    pub async fn create_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        tasks_c: Vec<TaskForCreate>,
+    ) -> Result<Vec<Result<i64>>> {
+        base::create_many::<Self, _>(ctx, mm, tasks_c).await
+    }
+
+    /// Updates the tasks of `ids`, see `base::update_many`.
+    pub async fn update_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        ids: &[i64],
+        task_u: TaskForUpdate,
+    ) -> Result<Vec<Result<i64>>> {
+        base::update_many::<Self, _>(ctx, mm, ids, task_u).await
+    }
+
+    /// Updates the tasks matching all the `filters`, one result per task.
+    pub async fn update_many_by_filters(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<Filter>,
+        task_u: TaskForUpdate,
+    ) -> Result<Vec<Result<i64>>> {
+        let ids = base::list_ids::<Self, Task>(ctx, mm, filters).await?;
+
+        base::update_many::<Self, _>(ctx, mm, &ids, task_u).await
+    }
+
+    /// Adds their labels to the `tasks`, with one query.
+    pub async fn with_labels(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        tasks: Vec<Task>,
+    ) -> Result<Vec<TaskWithLabels>> {
+        let ids: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+        let mut labels = LabelBmc::list_by_task_ids(ctx, mm, &ids).await?;
+
+        Ok(tasks
+            .into_iter()
+            .map(|task| TaskWithLabels {
+                labels: labels.remove(&task.id).unwrap_or_default(),
+                task,
+            })
+            .collect())
+    }
+
+    /// Task `id` and its subtasks at any depth, parents first.
+    pub async fn get_subtree(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<Task>> {
+        base::get_subtree::<Self, _>(ctx, mm, id).await
+    }
+
+    /// Moves the task under `parent_id`, or to the root tasks when `None`,
+    /// see `base::move_to_parent`.
+    pub async fn move_to_parent(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        parent_id: Option<i64>,
     ) -> Result<()> {
-        base::update::<Self, _>(ctx, &mm, id, task_u).await
+        base::move_to_parent::<Self>(ctx, mm, id, parent_id).await
+    }
+
+    /// Subtasks of the task `id` done, over all of them.
+    pub async fn progress(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<TaskProgress> {
+        // Not found or access denied.
+        Self::get(ctx, mm, id).await?;
+
+        let (done, total) =
+            base::count_descendants::<Self>(ctx, mm, id, "\"status\" = 'done'").await?;
+
+        Ok(TaskProgress { done, total })
+    }
+
+    /// Soft deletes the tasks of `ids`, their subtasks being moved under
+    /// their parent, see `base::delete_many`.
+    pub async fn delete_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        ids: &[i64],
+    ) -> Result<Vec<Result<i64>>> {
+        base::delete_many::<Self>(ctx, mm, ids).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::_dev_utils;
+    use crate::model::project::ProjectForCreate;
+    use crate::model::workspace::{WorkspaceBmc, WorkspaceForCreate};
+    use crate::model::Error;
 
     use super::*;
-    use anyhow::Result;
+    use anyhow::{Context, Result};
+    use serde_json::json;
     use serial_test::serial;
 
     #[serial]
     #[tokio::test]
     async fn test_create_ok() -> Result<()> {
         let mm = _dev_utils::init_test().await;
-        let ctx = Ctx::root_ctx();
+        let ctx = _dev_utils::ctx_test(0);
         let fx_title = "test_create_ok title";
 
         let task_c = TaskForCreate {
             title: fx_title.to_string(),
+            ..Default::default()
         };
         let id = TaskBmc::create(&ctx, &mm, task_c).await?;
 
-        let (title,): (String,) = sqlx::query_as("SELECT title FROM task WHERE id = $1")
+        let (title,): (String,) = sqlx::query_as("SELECT title FROM tasks WHERE id = $1")
             .bind(id)
-            .fetch_one(mm.db())
+            .fetch_one(&mut *mm.db(&ctx).await?)
             .await?;
 
         assert_eq!(title, fx_title);
 
-        let count = sqlx::query("DELETE FROM task WHERE id = $1")
+        let count = sqlx::query("DELETE FROM tasks WHERE id = $1")
             .bind(id)
-            .execute(mm.db())
+            .execute(&mut *mm.db(&ctx).await?)
             .await?
             .rows_affected();
 
@@ -94,11 +378,38 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_create_err_validation() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+
+        for fx_title in ["", "  ", &"a".repeat(256)] {
+            let task_c = TaskForCreate {
+                title: fx_title.to_string(),
+                ..Default::default()
+            };
+
+            let res = TaskBmc::create(&ctx, &mm, task_c).await;
+
+            assert!(
+                matches!(
+                    &res,
+                    Err(Error::ValidationFailed { entity: "tasks", errors })
+                        if errors.len() == 1 && errors[0].field == "title"
+                ),
+                "ValidationFailed not matching for title {fx_title:?}: {res:?}"
+            );
+        }
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_get_err_not_found() -> Result<()> {
         let mm = _dev_utils::init_test().await;
-        let ctx = Ctx::root_ctx();
+        let ctx = _dev_utils::ctx_test(0);
         let fx_id = 100;
 
         let res = TaskBmc::get(&ctx, &mm, fx_id).await;
@@ -107,7 +418,7 @@ mod tests {
             matches!(
                 res,
                 Err(Error::EntityNotFound {
-                    entity: "task",
+                    entity: "tasks",
                     id: 100
                 }),
             ),
@@ -121,7 +432,7 @@ mod tests {
     #[tokio::test]
     async fn test_delete_err_not_found() -> Result<()> {
         let mm = _dev_utils::init_test().await;
-        let ctx = Ctx::root_ctx();
+        let ctx = _dev_utils::ctx_test(0);
         let fx_id = 100;
 
         let res = TaskBmc::delete(&ctx, &mm, fx_id).await;
@@ -130,7 +441,7 @@ mod tests {
             matches!(
                 res,
                 Err(Error::EntityNotFound {
-                    entity: "task",
+                    entity: "tasks",
                     id: 100
                 }),
             ),
@@ -140,18 +451,65 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_delete_restore_purge_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_title = "test_delete_restore_purge_ok title";
+        let fx_task = _dev_utils::seed_test(&ctx, &mm, &[fx_title])
+            .await?
+            .remove(0);
+        let fx_filters: Vec<Filter> = serde_json::from_value(json!([
+            {"field": "title", "op": "eq", "value": fx_title},
+        ]))?;
+
+        TaskBmc::delete(&ctx, &mm, fx_task.id).await?;
+
+        let res = TaskBmc::get(&ctx, &mm, fx_task.id).await;
+        assert!(
+            matches!(res, Err(Error::EntityNotFound { .. })),
+            "deleted task should not be found"
+        );
+        let task = TaskBmc::get_include_deleted(&ctx, &mm, fx_task.id).await?;
+        assert!(task.deleted_at.is_some());
+
+        let page = TaskBmc::list(&ctx, &mm, fx_filters.clone(), ListOptions::default()).await?;
+        assert_eq!(page.total, 0, "deleted task not listed");
+        let list_options = ListOptions {
+            include_deleted: Some(true),
+            ..Default::default()
+        };
+        let page = TaskBmc::list(&ctx, &mm, fx_filters.clone(), list_options).await?;
+        assert_eq!(page.total, 1, "deleted task listed with include_deleted");
+
+        TaskBmc::restore(&ctx, &mm, fx_task.id).await?;
+        let task = TaskBmc::get(&ctx, &mm, fx_task.id).await?;
+        assert!(task.deleted_at.is_none());
+
+        TaskBmc::purge(&ctx, &mm, fx_task.id).await?;
+        let res = TaskBmc::get_include_deleted(&ctx, &mm, fx_task.id).await;
+        assert!(
+            matches!(res, Err(Error::EntityNotFound { .. })),
+            "purged task should not be found"
+        );
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_list_ok() -> Result<()> {
         let mm = _dev_utils::init_test().await;
-        let ctx = Ctx::root_ctx();
+        let ctx = _dev_utils::ctx_test(0);
         let fx_titles = ["test_list_ok title 1", "test_list_ok title 2"];
 
         _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
 
-        let tasks = TaskBmc::list(&ctx, &mm).await?;
+        let page = TaskBmc::list(&ctx, &mm, Vec::new(), ListOptions::default()).await?;
 
-        let tasks: Vec<Task> = tasks
+        let tasks: Vec<Task> = page
+            .items
             .into_iter()
             .filter(|t| t.title.starts_with("test_list_ok title"))
             .collect();
@@ -159,20 +517,335 @@ mod tests {
         assert_eq!(tasks.len(), 2, "number of seeded tasks");
 
         for task in tasks.iter() {
-            TaskBmc::delete(&ctx, &mm, task.id).await?;
+            TaskBmc::purge(&ctx, &mm, task.id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_paged_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_titles = [
+            "test_list_paged_ok 1",
+            "test_list_paged_ok 2",
+            "test_list_paged_ok 3",
+        ];
+
+        let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+
+        let list_options: ListOptions = serde_json::from_value(json!({
+            "limit": 2,
+            "order_bys": [{"field": "id", "direction": "desc"}],
+        }))?;
+        let page = TaskBmc::list(&ctx, &mm, Vec::new(), list_options).await?;
+
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].title, "test_list_paged_ok 3");
+        assert_eq!(page.items[1].title, "test_list_paged_ok 2");
+        assert!(page.total >= 3, "total of all the tasks");
+
+        let list_options: ListOptions = serde_json::from_value(json!({
+            "order_bys": [{"field": "unknown"}],
+        }))?;
+        let res = TaskBmc::list(&ctx, &mm, Vec::new(), list_options).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "order_bys"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        let list_options: ListOptions = serde_json::from_value(json!({"offset": u64::MAX}))?;
+        let res = TaskBmc::list(&ctx, &mm, Vec::new(), list_options).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "offset"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        for task in fx_tasks.iter() {
+            TaskBmc::purge(&ctx, &mm, task.id).await?;
         }
 
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_list_filtered_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_titles = [
+            "test_list_filtered_ok 1 50%",
+            "test_list_filtered_ok 2",
+            "test_list_filtered_ok 3",
+        ];
+
+        let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+
+        let filters: Vec<Filter> = serde_json::from_value(json!([
+            {"field": "title", "op": "startsWith", "value": "test_list_filtered_ok"},
+            {"or": [
+                {"field": "title", "op": "contains", "value": "50%"},
+                {"field": "id", "op": "in", "value": [fx_tasks[2].id]},
+            ]},
+        ]))?;
+        let page = TaskBmc::list(&ctx, &mm, filters, ListOptions::default()).await?;
+
+        let titles: Vec<&str> = page.items.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, [fx_titles[0], fx_titles[2]]);
+        assert_eq!(page.total, 2);
+
+        let filters: Vec<Filter> = serde_json::from_value(json!([
+            {"field": "title; DROP TABLE tasks", "op": "eq", "value": "x"},
+        ]))?;
+        let res = TaskBmc::list(&ctx, &mm, filters, ListOptions::default()).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "filters"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        for task in fx_tasks.iter() {
+            TaskBmc::purge(&ctx, &mm, task.id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_cursor_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_titles = [
+            "test_list_cursor_ok c",
+            "test_list_cursor_ok a",
+            "test_list_cursor_ok b",
+        ];
+
+        let fx_tasks = _dev_utils::seed_test(&ctx, &mm, &fx_titles).await?;
+        let fx_filters = json!([
+            {"field": "title", "op": "startsWith", "value": "test_list_cursor_ok"},
+        ]);
+        let fx_order_bys = json!([{"field": "title", "direction": "desc"}]);
+
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(fx_filters.clone())?,
+            serde_json::from_value(json!({"limit": 2, "order_bys": fx_order_bys}))?,
+        )
+        .await?;
+        let titles: Vec<&str> = page.items.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, [fx_titles[0], fx_titles[2]]);
+        let cursor = page.next_cursor.context("Should have next_cursor")?;
+
+        // Inserted before the cursor position, must not shift the next page.
+        let fx_new = _dev_utils::seed_test(&ctx, &mm, &["test_list_cursor_ok d"]).await?;
+
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(fx_filters.clone())?,
+            serde_json::from_value(
+                json!({"limit": 2, "cursor": cursor, "order_bys": fx_order_bys}),
+            )?,
+        )
+        .await?;
+        let titles: Vec<&str> = page.items.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, [fx_titles[1]]);
+        assert!(page.next_cursor.is_none(), "last page");
+
+        let fx_tampered = format!("x{cursor}");
+        let res = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(fx_filters)?,
+            serde_json::from_value(json!({"cursor": fx_tampered, "order_bys": fx_order_bys}))?,
+        )
+        .await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "cursor"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        for task in fx_tasks.iter().chain(fx_new.iter()) {
+            TaskBmc::purge(&ctx, &mm, task.id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_by_status_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_prefix = "test_list_by_status_ok";
+        let fx_tasks_c: Vec<TaskForCreate> = serde_json::from_value(json!([
+            {"title": format!("{fx_prefix} a"), "status": "done", "priority": 1,
+             "description": "2030-01-01T00:00:00Z"},
+            {"title": format!("{fx_prefix} b"), "due_at": "2030-01-01T00:00:00Z"},
+            {"title": format!("{fx_prefix} c"), "status": "in_progress", "priority": 3},
+        ]))?;
+        let mut ids = Vec::new();
+        for task_c in fx_tasks_c {
+            ids.push(TaskBmc::create(&ctx, &mm, task_c).await?);
+        }
+        let fx_filters = json!([{"field": "title", "op": "startsWith", "value": fx_prefix}]);
+        let fx_order_bys = json!([{"field": "status", "direction": "desc"}]);
+
+        // Enum order, not text order, across the cursor.
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(fx_filters.clone())?,
+            serde_json::from_value(json!({"limit": 2, "order_bys": fx_order_bys}))?,
+        )
+        .await?;
+        let statuses: Vec<TaskStatus> = page.items.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, [TaskStatus::Done, TaskStatus::InProgress]);
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(fx_filters.clone())?,
+            serde_json::from_value(json!({
+                "cursor": page.next_cursor.context("Should have next_cursor")?,
+                "order_bys": fx_order_bys
+            }))?,
+        )
+        .await?;
+        let statuses: Vec<TaskStatus> = page.items.iter().map(|t| t.status).collect();
+        assert_eq!(statuses, [TaskStatus::Todo]);
+        assert_eq!(page.items[0].priority, 0);
+
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(json!([
+                fx_filters[0],
+                {"field": "status", "op": "in", "value": ["todo", "in_progress"]},
+                {"field": "due_at", "op": "lt", "value": "2031-01-01T00:00:00Z"},
+            ]))?,
+            Default::default(),
+        )
+        .await?;
+        assert_eq!(page.total, 1);
+        let task_id = page.items[0].id;
+
+        // A text field compared as text, even with a timestamp like value.
+        let page = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(json!([
+                fx_filters[0],
+                {"field": "description", "op": "eq", "value": "2030-01-01T00:00:00Z"},
+            ]))?,
+            Default::default(),
+        )
+        .await?;
+        assert_eq!(page.total, 1);
+        assert_eq!(page.items[0].status, TaskStatus::Done);
+
+        let task_u: TaskForUpdate = serde_json::from_value(json!({"description": null}))?;
+        TaskBmc::update(&ctx, &mm, page.items[0].id, task_u, None).await?;
+        let task = TaskBmc::get(&ctx, &mm, page.items[0].id).await?;
+        assert_eq!(task.description, None);
+
+        let task_u: TaskForUpdate = serde_json::from_value(json!({"due_at": null}))?;
+        TaskBmc::update(&ctx, &mm, task_id, task_u, None).await?;
+        let task = TaskBmc::get(&ctx, &mm, task_id).await?;
+        assert_eq!(task.due_at, None);
+
+        let res = TaskBmc::list(
+            &ctx,
+            &mm,
+            serde_json::from_value(json!([{"field": "status", "op": "eq", "value": "started"}]))?,
+            Default::default(),
+        )
+        .await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "filters"),
+            "ValidationFailed not matching: {res:?}"
+        );
+        let task_u: TaskForUpdate = serde_json::from_value(json!({"priority": 4}))?;
+        let res = TaskBmc::update(&ctx, &mm, task_id, task_u, None).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "priority"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        for id in ids {
+            TaskBmc::purge(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_transaction_rollback_commit() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = &_dev_utils::ctx_test(0);
+        let fx_title = "test_transaction_rollback_commit title";
+        let fx_filters = || -> Result<Vec<Filter>> {
+            Ok(serde_json::from_value(json!([
+                {"field": "title", "op": "eq", "value": fx_title},
+            ]))?)
+        };
+        let mut events = mm.subscribe_events();
+
+        // -- Rollback on error.
+        let res: crate::model::Result<()> = mm
+            .transaction(|mm| async move {
+                let task_c = TaskForCreate {
+                    title: fx_title.to_string(),
+                    ..Default::default()
+                };
+                let id = TaskBmc::create(ctx, &mm, task_c).await?;
+                TaskBmc::get(ctx, &mm, id).await?;
+
+                Err(Error::EntityNotFound {
+                    entity: "tasks",
+                    id: 0,
+                })
+            })
+            .await;
+        assert!(matches!(res, Err(Error::EntityNotFound { id: 0, .. })));
+
+        let page = TaskBmc::list(ctx, &mm, fx_filters()?, ListOptions::default()).await?;
+        assert_eq!(page.total, 0, "create rolled back");
+        assert!(events.try_recv().is_err(), "no event for a rollback");
+
+        // -- Commit on ok.
+        let id = mm
+            .transaction(|mm| async move {
+                let task_c = TaskForCreate {
+                    title: fx_title.to_string(),
+                    ..Default::default()
+                };
+                TaskBmc::create(ctx, &mm, task_c).await
+            })
+            .await?;
+
+        let task = TaskBmc::get(ctx, &mm, id).await?;
+        assert_eq!(task.title, fx_title);
+        assert_eq!(events.try_recv()?.entity_id, id, "event after commit");
+
+        TaskBmc::purge(ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_update_ok() -> Result<()> {
         let mm = _dev_utils::init_test().await;
-        let ctx = Ctx::root_ctx();
+        let ctx = _dev_utils::ctx_test(0);
+        let ctx_user = _dev_utils::ctx_test(1000);
         let fx_title = "test_update_ok title";
         let fx_title_updated = "test_update_ok title updated";
-        let fx_task = _dev_utils::seed_test(&ctx, &mm, &[fx_title])
+        let fx_task = _dev_utils::seed_test(&ctx_user, &mm, &[fx_title])
             .await?
             .remove(0);
 
@@ -182,12 +855,427 @@ mod tests {
             fx_task.id,
             TaskForUpdate {
                 title: Some(fx_title_updated.to_string()),
+                ..Default::default()
             },
-        );
+            None,
+        )
+        .await?;
 
-        let task = TaskBmc::get(&ctx, &mm, fx_task.id).await?;
+        let task = TaskBmc::get(&ctx_user, &mm, fx_task.id).await?;
 
         assert_eq!(task.title, fx_title_updated);
+        assert_eq!(task.owner_id, 1000, "owned by user");
+        assert_eq!(task.cid, 1000, "created by user");
+        assert_eq!(task.mid, 0, "modified by root");
+        assert_eq!(task.ctime, fx_task.ctime);
+        assert!(task.mtime > fx_task.mtime, "mtime updated");
+        assert_eq!(task.version, fx_task.version + 1);
+
+        TaskBmc::purge(&ctx, &mm, fx_task.id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_timestamps_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let ctx_user = _dev_utils::ctx_test(1000);
+        // Stored to the microsecond.
+        let before = crate::util::now_utc() - time::Duration::SECOND;
+
+        let task_c = TaskForCreate {
+            title: "test_timestamps_ok".to_string(),
+            ..Default::default()
+        };
+        let id = TaskBmc::create(&ctx_user, &mm, task_c).await?;
+        let task = TaskBmc::get(&ctx_user, &mm, id).await?;
+
+        // -- Check create
+        assert_eq!((task.cid, task.mid), (1000, 1000));
+        assert_eq!(task.ctime, task.mtime);
+        assert!(task.ctime >= before && task.ctime <= crate::util::now_utc());
+
+        // -- Check update, only mid and mtime changed
+        let task_u = TaskForUpdate {
+            priority: Some(1),
+            ..Default::default()
+        };
+        TaskBmc::update(&ctx, &mm, id, task_u, None).await?;
+        let task_updated = TaskBmc::get(&ctx_user, &mm, id).await?;
+        assert_eq!(
+            (task_updated.cid, task_updated.ctime),
+            (task.cid, task.ctime)
+        );
+        assert_eq!(task_updated.mid, 0);
+        assert!(task_updated.mtime > task.mtime);
+
+        TaskBmc::purge(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_rls_hides_other_rows() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx_owner = _dev_utils::ctx_test(1000);
+        let ctx_other = _dev_utils::ctx_test(1001);
+        let fx_task = _dev_utils::seed_test(&ctx_owner, &mm, &["test_rls_hides_other_rows"])
+            .await?
+            .remove(0);
+        let fx_sql = "SELECT id FROM tasks WHERE id = $1";
+
+        // Unscoped queries, only the row level security applies.
+        let row: Option<(i64,)> = sqlx::query_as(fx_sql)
+            .bind(fx_task.id)
+            .fetch_optional(&mut *mm.db(&ctx_other).await?)
+            .await?;
+        assert!(row.is_none(), "hidden to other");
+
+        let row: Option<(i64,)> = mm
+            .transaction(|mm| async move {
+                let row = sqlx::query_as(fx_sql)
+                    .bind(fx_task.id)
+                    .fetch_optional(&mut *mm.db(&ctx_owner).await?)
+                    .await?;
+                crate::model::Result::Ok(row)
+            })
+            .await?;
+        assert!(row.is_some(), "visible to owner in a transaction");
+
+        TaskBmc::purge(&_dev_utils::ctx_test(0), &mm, fx_task.id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_access_denied() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx_owner = _dev_utils::ctx_test(1000);
+        let ctx_other = _dev_utils::ctx_test(1001);
+        let fx_title = "test_access_denied title";
+        let fx_task = _dev_utils::seed_test(&ctx_owner, &mm, &[fx_title])
+            .await?
+            .remove(0);
+        let is_denied = |res: &crate::model::Result<_>| matches!(res, Err(Error::AccessDenied { id, .. }) if *id == fx_task.id);
+
+        let res = TaskBmc::get(&ctx_other, &mm, fx_task.id).await;
+        assert!(is_denied(&res.map(|_| ())), "get denied");
+        let task_u = TaskForUpdate {
+            title: Some("test_access_denied title updated".to_string()),
+            ..Default::default()
+        };
+        let res = TaskBmc::update(&ctx_other, &mm, fx_task.id, task_u, None).await;
+        assert!(is_denied(&res), "update denied");
+        let res = TaskBmc::delete(&ctx_other, &mm, fx_task.id).await;
+        assert!(is_denied(&res), "delete denied");
+
+        let fx_filters = serde_json::from_value::<Vec<Filter>>(json!([
+            {"field": "title", "op": "eq", "value": fx_title},
+        ]))?;
+        let page =
+            TaskBmc::list(&ctx_other, &mm, fx_filters.clone(), ListOptions::default()).await?;
+        assert_eq!(page.total, 0, "not listed for other");
+        let page = TaskBmc::list(&ctx_owner, &mm, fx_filters, ListOptions::default()).await?;
+        assert_eq!(page.total, 1, "listed for owner");
+
+        let task = TaskBmc::get(&_dev_utils::ctx_test(0), &mm, fx_task.id).await?;
+        assert_eq!(task.title, fx_title, "unchanged");
+
+        TaskBmc::purge(&ctx_owner, &mm, fx_task.id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_workspace_scoping() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let fx_task = _dev_utils::seed_test(&ctx, &mm, &["test_workspace_scoping title"])
+            .await?
+            .remove(0);
+        let workspace_id = WorkspaceBmc::create(
+            &ctx,
+            &mm,
+            WorkspaceForCreate {
+                name: "test_workspace_scoping workspace".to_string(),
+            },
+        )
+        .await?;
+        let ctx_other_workspace = ctx.clone().with_workspace_id(Some(workspace_id));
+
+        let res = TaskBmc::get(&ctx_other_workspace, &mm, fx_task.id).await;
+        assert!(
+            matches!(res, Err(Error::EntityNotFound { id, .. }) if id == fx_task.id),
+            "EntityNotFound not matching: {res:?}"
+        );
+        let tasks = TaskBmc::list(&ctx_other_workspace, &mm, vec![], Default::default()).await?;
+        assert!(tasks.items.is_empty(), "no task in the new workspace");
+
+        let res = TaskBmc::get(&ctx.clone().with_workspace_id(None), &mm, fx_task.id).await;
+        assert!(
+            matches!(res, Err(Error::WorkspaceRequired { .. })),
+            "WorkspaceRequired not matching: {res:?}"
+        );
+
+        TaskBmc::purge(&ctx, &mm, fx_task.id).await?;
+        sqlx::query("DELETE FROM workspaces WHERE id = $1")
+            .bind(workspace_id)
+            .execute(&mut *mm.db(&Ctx::root_ctx()).await?)
+            .await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_err_conflict() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_task = _dev_utils::seed_test(&ctx, &mm, &["test_update_err_conflict title"])
+            .await?
+            .remove(0);
+        let fx_task_u = || TaskForUpdate {
+            title: Some("test_update_err_conflict title updated".to_string()),
+            ..Default::default()
+        };
+
+        TaskBmc::update(&ctx, &mm, fx_task.id, fx_task_u(), Some(fx_task.version)).await?;
+
+        let res = TaskBmc::update(&ctx, &mm, fx_task.id, fx_task_u(), Some(fx_task.version)).await;
+
+        assert!(
+            matches!(
+                &res,
+                Err(Error::Conflict { current: Some(current), .. })
+                    if current["version"] == fx_task.version + 1
+            ),
+            "Conflict not matching: {res:?}"
+        );
+
+        TaskBmc::purge(&ctx, &mm, fx_task.id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_update_delete_many_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(0);
+        let fx_prefix = "test_create_update_delete_many_ok";
+        let fx_tasks_c = vec![
+            TaskForCreate {
+                title: format!("{fx_prefix} 01"),
+                ..Default::default()
+            },
+            TaskForCreate {
+                title: "".to_string(),
+                ..Default::default()
+            },
+            TaskForCreate {
+                title: format!("{fx_prefix} 02"),
+                ..Default::default()
+            },
+        ];
+
+        // -- Create, the invalid item is skipped.
+        let results = TaskBmc::create_many(&ctx, &mm, fx_tasks_c).await?;
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[1], Err(Error::ValidationFailed { .. })));
+        let ids: Vec<i64> = results.into_iter().filter_map(|res| res.ok()).collect();
+        assert_eq!(ids.len(), 2);
+
+        // -- Update by filters.
+        let fx_filters = serde_json::from_value(json!([
+            {"field": "title", "op": "startsWith", "value": fx_prefix},
+        ]))?;
+        let fx_task_u = TaskForUpdate {
+            title: Some(format!("{fx_prefix} updated")),
+            ..Default::default()
+        };
+        let mut updated_ids = TaskBmc::update_many_by_filters(&ctx, &mm, fx_filters, fx_task_u)
+            .await?
+            .into_iter()
+            .collect::<crate::model::Result<Vec<i64>>>()?;
+        updated_ids.sort();
+        assert_eq!(updated_ids, ids);
+
+        let tasks = TaskBmc::get_many(&ctx, &mm, &ids, false).await?;
+        assert_eq!(tasks.len(), 2);
+        for task in &tasks {
+            assert_eq!(task.title, format!("{fx_prefix} updated"));
+            assert_eq!(task.version, 1);
+        }
+
+        // -- Delete, the unknown id is not deleted.
+        let results = TaskBmc::delete_many(&ctx, &mm, &[ids[0], 100]).await?;
+        assert!(matches!(results[0], Ok(id) if id == ids[0]));
+        assert!(matches!(
+            results[1],
+            Err(Error::EntityNotFound { id: 100, .. })
+        ));
+        let results = TaskBmc::update_many(&ctx, &mm, &ids, TaskForUpdate::default()).await?;
+        assert!(
+            matches!(results[0], Err(Error::EntityNotFound { .. })),
+            "deleted task not updated"
+        );
+        assert!(matches!(results[1], Ok(id) if id == ids[1]));
+
+        for id in ids {
+            TaskBmc::purge(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_subtree_move_delete_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let fx_root = _dev_utils::seed_test(&ctx, &mm, &["test_subtree_move_delete_ok root"])
+            .await?
+            .remove(0);
+        let mut parent_id = fx_root.id;
+        let mut fx_ids = vec![fx_root.id];
+        for (title, status) in [
+            ("child", TaskStatus::Todo),
+            ("grandchild", TaskStatus::Done),
+        ] {
+            let task_c = TaskForCreate {
+                title: format!("test_subtree_move_delete_ok {title}"),
+                parent_id: Some(parent_id),
+                status: Some(status),
+                ..Default::default()
+            };
+            parent_id = TaskBmc::create(&ctx, &mm, task_c).await?;
+            fx_ids.push(parent_id);
+        }
+        let (root_id, child_id, grandchild_id) = (fx_ids[0], fx_ids[1], fx_ids[2]);
+
+        let subtree = TaskBmc::get_subtree(&ctx, &mm, root_id).await?;
+        let ids: Vec<i64> = subtree.iter().map(|task| task.id).collect();
+        assert_eq!(ids, fx_ids, "parents first");
+        let progress = TaskBmc::progress(&ctx, &mm, root_id).await?;
+        assert_eq!((progress.done, progress.total), (1, 2));
+
+        let res = TaskBmc::move_to_parent(&ctx, &mm, root_id, Some(grandchild_id)).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].rule == "no_cycle"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        // Parent of another owner.
+        let task_c = TaskForCreate {
+            title: "test_subtree_move_delete_ok other".to_string(),
+            parent_id: Some(root_id),
+            ..Default::default()
+        };
+        let res = TaskBmc::create(&_dev_utils::ctx_test(1001), &mm, task_c).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "parent_id"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        TaskBmc::delete(&ctx, &mm, child_id).await?;
+        let grandchild = TaskBmc::get(&ctx, &mm, grandchild_id).await?;
+        assert_eq!(grandchild.parent_id, Some(root_id), "moved under the root");
+
+        // Deleted parent.
+        let task_c = TaskForCreate {
+            title: "test_subtree_move_delete_ok deleted parent".to_string(),
+            parent_id: Some(child_id),
+            ..Default::default()
+        };
+        let res = TaskBmc::create(&ctx, &mm, task_c).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "parent_id"),
+            "ValidationFailed not matching: {res:?}"
+        );
+        let res = TaskBmc::move_to_parent(&ctx, &mm, grandchild_id, Some(child_id)).await;
+        assert!(
+            matches!(&res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "parent_id"),
+            "ValidationFailed not matching: {res:?}"
+        );
+
+        TaskBmc::move_to_parent(&ctx, &mm, grandchild_id, None).await?;
+        let progress = TaskBmc::progress(&ctx, &mm, root_id).await?;
+        assert_eq!((progress.done, progress.total), (0, 0));
+
+        for id in fx_ids {
+            TaskBmc::purge(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_update_many_err_reference() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let fx_prefix = "test_create_update_many_err_reference";
+        let fx_project_id = ProjectBmc::create(
+            &ctx,
+            &mm,
+            ProjectForCreate {
+                name: fx_prefix.to_string(),
+            },
+        )
+        .await?;
+        let is_reference_error = |res: &crate::model::Result<i64>| matches!(res, Err(Error::ValidationFailed { errors, .. }) if errors[0].field == "project_id");
+
+        // -- Create, the item with an unknown project is skipped.
+        let fx_tasks_c = vec![
+            TaskForCreate {
+                title: format!("{fx_prefix} 01"),
+                project_id: Some(fx_project_id),
+                ..Default::default()
+            },
+            TaskForCreate {
+                title: format!("{fx_prefix} 02"),
+                project_id: Some(100),
+                ..Default::default()
+            },
+            TaskForCreate {
+                title: format!("{fx_prefix} 03"),
+                ..Default::default()
+            },
+        ];
+        let results = TaskBmc::create_many(&ctx, &mm, fx_tasks_c).await?;
+        assert!(results[0].is_ok());
+        assert!(is_reference_error(&results[1]), "{:?}", results[1]);
+        assert!(results[2].is_ok());
+        let ids: Vec<i64> = results.into_iter().filter_map(|res| res.ok()).collect();
+
+        // -- Update, failing per item.
+        let fx_task_u = TaskForUpdate {
+            project_id: Some(100),
+            ..Default::default()
+        };
+        let results = TaskBmc::update_many(&ctx, &mm, &ids, fx_task_u).await?;
+        assert!(is_reference_error(&results[0]), "{:?}", results[0]);
+        assert!(is_reference_error(&results[1]), "{:?}", results[1]);
+        let fx_task_u = TaskForUpdate {
+            project_id: Some(fx_project_id),
+            ..Default::default()
+        };
+        let results = TaskBmc::update_many(&ctx, &mm, &ids, fx_task_u).await?;
+        assert!(results.iter().all(|res| res.is_ok()), "{results:?}");
+
+        for id in ids {
+            TaskBmc::purge(&ctx, &mm, id).await?;
+        }
+        let mut db = mm.db(&Ctx::root_ctx()).await?;
+        sqlx::query("DELETE FROM projects WHERE id = $1")
+            .bind(fx_project_id)
+            .execute(&mut *db)
+            .await?;
 
         Ok(())
     }
@@ -4,8 +4,8 @@ use crate::model::{
     base::{self, DbBmc},
     ModelManager, Result,
 };
-use serde::{Deserialize, Serialize};
-use sqlb::{Fields, HasFields, SqlBuilder, Whereable};
+use serde::Serialize;
+use sqlb::{Fields, HasFields};
 use sqlx::postgres::PgRow;
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -16,17 +16,6 @@ pub struct User {
     pub username: String,
 }
 
-#[derive(Deserialize)]
-pub struct UserForCreate {
-    pub username: String,
-    pub password_clear: String,
-}
-
-#[derive(Fields, Deserialize)]
-struct UserForInsert {
-    username: String,
-}
-
 #[derive(Clone, Debug, FromRow, Fields)]
 pub struct UserForLogin {
     pub id: i64,
@@ -41,6 +30,8 @@ pub struct UserForAuth {
     pub id: i64,
     pub username: String,
     pub token_salt: Uuid,
+    /// Default workspace, see `update_workspace`.
+    pub workspace_id: Option<i64>,
 }
 
 pub trait UserBy: HasFields + for<'r> FromRow<'r, PgRow> + Unpin + Send {}
@@ -64,19 +55,19 @@ impl UserBmc {
     }
 
     pub async fn first_by_username<E>(
-        _ctx: &Ctx,
+        ctx: &Ctx,
         mm: &ModelManager,
         username: &str,
     ) -> Result<Option<E>>
     where
         E: UserBy,
     {
-        let db = mm.db();
+        let mut db = mm.db(ctx).await?;
 
         let user = sqlb::select()
             .table(UserBmc::TABLE)
             .and_where("username", "=", username)
-            .fetch_optional::<_, E>(db)
+            .fetch_optional::<_, E>(&mut *db)
             .await?;
 
         Ok(user)
@@ -88,8 +79,6 @@ impl UserBmc {
         id: i64,
         password_clear: &str,
     ) -> Result<()> {
-        let db = mm.db();
-
         let user: UserForLogin = Self::get(ctx, mm, id).await?;
 
         let pwd = pwd::encrypt_pwd(&crypt::EncryptContent {
@@ -97,11 +86,37 @@ impl UserBmc {
             salt: user.pwd_salt.to_string(),
         })?;
 
+        let mut fields = vec![("pwd", pwd.to_string()).into()];
+        base::add_timestamps_for_update(&mut fields, ctx.user_id());
+
+        let mut db = mm.db(ctx).await?;
+        sqlb::update()
+            .table(Self::TABLE)
+            .and_where("id", "=", id)
+            .data(fields)
+            .exec(&mut *db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Sets the default workspace of the user, the one of its sessions
+    /// without workspace chosen (and requests without workspace header).
+    pub async fn update_workspace(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        workspace_id: Option<i64>,
+    ) -> Result<()> {
+        let mut fields = vec![("workspace_id", workspace_id).into()];
+        base::add_timestamps_for_update(&mut fields, ctx.user_id());
+
+        let mut db = mm.db(ctx).await?;
         sqlb::update()
             .table(Self::TABLE)
             .and_where("id", "=", id)
-            .data(vec![("pwd", pwd.to_string()).into()])
-            .exec(db)
+            .data(fields)
+            .exec(&mut *db)
             .await?;
 
         Ok(())
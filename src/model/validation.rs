@@ -0,0 +1,95 @@
+use serde::Serialize;
+
+/// Implemented by the `*ForCreate` / `*ForUpdate` types, validated by
+/// `base::create` and `base::update` before reaching the database.
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+/// Field that broke a validation rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Collects the `FieldError`s of the rules checked on a value.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    /// Value must contain a non whitespace character.
+    pub fn not_blank(&mut self, field: &'static str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.invalid(field, "not_blank", "must not be empty or blank".to_string());
+        }
+        self
+    }
+
+    /// Value must be at most `max` characters (as for a `VARCHAR(max)`).
+    pub fn max_chars(&mut self, field: &'static str, value: &str, max: usize) -> &mut Self {
+        if value.chars().count() > max {
+            self.invalid(
+                field,
+                "max_chars",
+                format!("must be at most {max} characters"),
+            );
+        }
+        self
+    }
+
+    /// Value must be between `min` and `max`, inclusive.
+    pub fn range(&mut self, field: &'static str, value: i64, min: i64, max: i64) -> &mut Self {
+        if !(min..=max).contains(&value) {
+            self.invalid(field, "range", format!("must be between {min} and {max}"));
+        }
+        self
+    }
+
+    /// Value must be one of `allowed`.
+    pub fn one_of(&mut self, field: &'static str, value: &str, allowed: &[&str]) -> &mut Self {
+        if !allowed.contains(&value) {
+            self.invalid(
+                field,
+                "one_of",
+                format!("'{value}' must be one of: {}", allowed.join(", ")),
+            );
+        }
+        self
+    }
+
+    /// Ok if no rule was broken, otherwise all the broken rules.
+    pub fn finish(self) -> core::result::Result<(), Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    /// Adds a broken rule, for the checks not covered by the other rules.
+    pub fn invalid(
+        &mut self,
+        field: &'static str,
+        rule: &'static str,
+        message: String,
+    ) -> &mut Self {
+        self.errors.push(FieldError {
+            field,
+            rule,
+            message,
+        });
+        self
+    }
+}
+
+/// Runs the `data` validation, returning all the broken rules.
+pub fn validate<E: Validate>(data: &E) -> core::result::Result<(), Vec<FieldError>> {
+    let mut v = Validator::default();
+    data.validate(&mut v);
+
+    v.finish()
+}
@@ -0,0 +1,402 @@
+use crate::ctx::Ctx;
+use crate::model::{
+    base::{self, DbBmc},
+    user::{User, UserBmc},
+    validation::{Validate, Validator},
+    Error, ModelManager, Result,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlb::Fields;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Clone, Debug, Fields, FromRow, Serialize, JsonSchema)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+
+    pub cid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(with = "String")]
+    pub mtime: OffsetDateTime,
+}
+
+#[derive(Fields, Deserialize, JsonSchema)]
+pub struct WorkspaceForCreate {
+    pub name: String,
+}
+
+const NAME_MAX_CHARS: usize = 255;
+
+impl Validate for WorkspaceForCreate {
+    fn validate(&self, v: &mut Validator) {
+        v.not_blank("name", &self.name)
+            .max_chars("name", &self.name, NAME_MAX_CHARS);
+    }
+}
+
+/// Role of a user in a workspace, the owner being its creator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "workspace_role", rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Owner,
+    Member,
+}
+
+/// Workspace of the ctx user, with its role in it.
+#[derive(Debug, FromRow, Serialize, JsonSchema)]
+pub struct UserWorkspace {
+    pub id: i64,
+    pub name: String,
+    pub role: WorkspaceRole,
+    /// Whether it is the ctx workspace.
+    pub current: bool,
+}
+
+/// Member of a workspace, with its role in it.
+#[derive(Debug, FromRow, Serialize, JsonSchema)]
+pub struct WorkspaceMember {
+    pub user_id: i64,
+    pub username: String,
+    pub role: WorkspaceRole,
+}
+
+pub struct WorkspaceBmc;
+
+impl DbBmc for WorkspaceBmc {
+    const TABLE: &'static str = "workspaces";
+    const IS_WORKSPACE: bool = true;
+}
+
+const MEMBERS_TABLE: &str = "workspace_members";
+
+impl WorkspaceBmc {
+    /// Creates a workspace, with the ctx user as its owner.
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        workspace_c: WorkspaceForCreate,
+    ) -> Result<i64> {
+        mm.transaction(|mm| async move {
+            let id = base::create::<Self, _>(ctx, &mm, workspace_c).await?;
+            Self::insert_member(ctx, &mm, id, ctx.user_id(), WorkspaceRole::Owner).await?;
+
+            Ok(id)
+        })
+        .await
+    }
+
+    /// Workspace `id` of the ctx user, `AccessDenied` if not a member.
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<UserWorkspace> {
+        let workspace = Self::list_user_workspaces(ctx, mm, Some(id)).await?.pop();
+
+        match workspace {
+            Some(workspace) => Ok(workspace),
+            None => {
+                // Not found if it does not exist.
+                base::get::<Self, Workspace>(ctx, mm, id).await?;
+
+                Err(Error::AccessDenied {
+                    entity: Self::TABLE,
+                    id,
+                })
+            }
+        }
+    }
+
+    /// Workspaces of the ctx user, by name.
+    pub async fn list(ctx: &Ctx, mm: &ModelManager) -> Result<Vec<UserWorkspace>> {
+        Self::list_user_workspaces(ctx, mm, None).await
+    }
+
+    /// Role of the ctx user in the workspace `id`, `None` if not a member.
+    pub async fn role(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Option<WorkspaceRole>> {
+        Self::member_role(ctx, mm, id, ctx.user_id()).await
+    }
+
+    /// Members of the workspace `id`, by username, for a member of it.
+    pub async fn list_members(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> Result<Vec<WorkspaceMember>> {
+        if !ctx.is_root() {
+            // Checks the membership.
+            Self::get(ctx, mm, id).await?;
+        }
+
+        let mut db = mm.db(ctx).await?;
+        let members = sqlx::query_as(&format!(
+            "SELECT m.user_id, u.username, m.role \
+             FROM {MEMBERS_TABLE} m JOIN {} u ON u.id = m.user_id \
+             WHERE m.workspace_id = $1 ORDER BY u.username, u.id",
+            UserBmc::TABLE
+        ))
+        .bind(id)
+        .fetch_all(&mut *db)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// Adds the user `user_id` as a member of the workspace `id` (no-op if
+    /// already one), for an owner of it.
+    pub async fn add_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+        Self::check_owner(ctx, mm, id).await?;
+        // Not found if the user does not exist.
+        UserBmc::get::<User>(ctx, mm, user_id).await?;
+
+        Self::insert_member(ctx, mm, id, user_id, WorkspaceRole::Member).await
+    }
+
+    /// Removes the member `user_id` from the workspace `id`, for an owner
+    /// of it. The owners cannot be removed.
+    pub async fn remove_member(ctx: &Ctx, mm: &ModelManager, id: i64, user_id: i64) -> Result<()> {
+        Self::check_owner(ctx, mm, id).await?;
+
+        match Self::member_role(ctx, mm, id, user_id).await? {
+            None => {
+                return Err(Error::EntityNotFound {
+                    entity: MEMBERS_TABLE,
+                    id: user_id,
+                })
+            }
+            Some(WorkspaceRole::Owner) => {
+                return Err(Error::AccessDenied {
+                    entity: MEMBERS_TABLE,
+                    id: user_id,
+                })
+            }
+            Some(WorkspaceRole::Member) => (),
+        }
+
+        let mut db = mm.db(ctx).await?;
+        sqlx::query(&format!(
+            "DELETE FROM {MEMBERS_TABLE} WHERE workspace_id = $1 AND user_id = $2"
+        ))
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut *db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Makes the workspace `id` the default one of the ctx user, for its
+    /// sessions without workspace chosen.
+    pub async fn set_default(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        // Checks the membership.
+        Self::get(ctx, mm, id).await?;
+
+        UserBmc::update_workspace(ctx, mm, ctx.user_id(), Some(id)).await
+    }
+
+    /// `AccessDenied` (or not found) unless the ctx user is an owner of
+    /// the workspace `id`.
+    async fn check_owner(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        if ctx.is_root() {
+            return Ok(());
+        }
+
+        match Self::role(ctx, mm, id).await? {
+            Some(WorkspaceRole::Owner) => Ok(()),
+            Some(WorkspaceRole::Member) => Err(Error::AccessDenied {
+                entity: Self::TABLE,
+                id,
+            }),
+            // Not found or access denied.
+            None => Self::get(ctx, mm, id).await.map(|_| ()),
+        }
+    }
+
+    async fn member_role(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        workspace_id: i64,
+        user_id: i64,
+    ) -> Result<Option<WorkspaceRole>> {
+        let mut db = mm.db(ctx).await?;
+
+        let role: Option<(WorkspaceRole,)> = sqlx::query_as(&format!(
+            "SELECT role FROM {MEMBERS_TABLE} WHERE workspace_id = $1 AND user_id = $2"
+        ))
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_optional(&mut *db)
+        .await?;
+
+        Ok(role.map(|(role,)| role))
+    }
+
+    async fn insert_member(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        workspace_id: i64,
+        user_id: i64,
+        role: WorkspaceRole,
+    ) -> Result<()> {
+        let mut db = mm.db(ctx).await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO {MEMBERS_TABLE} (workspace_id, user_id, role, cid, ctime) \
+             VALUES ($1, $2, $3, $4, now()) ON CONFLICT DO NOTHING"
+        ))
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(role)
+        .bind(ctx.user_id())
+        .execute(&mut *db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn list_user_workspaces(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: Option<i64>,
+    ) -> Result<Vec<UserWorkspace>> {
+        let mut db = mm.db(ctx).await?;
+
+        let workspaces = sqlx::query_as(&format!(
+            "SELECT w.id, w.name, m.role, w.id IS NOT DISTINCT FROM $2 AS current \
+             FROM {} w JOIN {MEMBERS_TABLE} m ON m.workspace_id = w.id \
+             WHERE m.user_id = $1 AND ($3::BIGINT IS NULL OR w.id = $3) \
+             ORDER BY w.name, w.id",
+            Self::TABLE
+        ))
+        .bind(ctx.user_id())
+        .bind(ctx.workspace_id())
+        .bind(id)
+        .fetch_all(&mut *db)
+        .await?;
+
+        Ok(workspaces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+
+    use super::*;
+    use anyhow::Result;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_set_default_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::new(1000)?;
+        let ctx_other = Ctx::new(1001)?;
+        let fx_name = "test_create_set_default_ok workspace";
+
+        let id = WorkspaceBmc::create(
+            &ctx,
+            &mm,
+            WorkspaceForCreate {
+                name: fx_name.to_string(),
+            },
+        )
+        .await?;
+
+        let workspace = WorkspaceBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(workspace.name, fx_name);
+        assert_eq!(workspace.role, WorkspaceRole::Owner);
+        assert!(!workspace.current);
+
+        let res = WorkspaceBmc::set_default(&ctx_other, &mm, id).await;
+        assert!(
+            matches!(res, Err(Error::AccessDenied { .. })),
+            "AccessDenied not matching: {res:?}"
+        );
+
+        WorkspaceBmc::set_default(&ctx, &mm, id).await?;
+        let workspaces = WorkspaceBmc::list(&ctx.clone().with_workspace_id(Some(id)), &mm).await?;
+        let current: Vec<&str> = workspaces
+            .iter()
+            .filter(|workspace| workspace.current)
+            .map(|workspace| workspace.name.as_str())
+            .collect();
+        assert_eq!(current, [fx_name]);
+
+        // -- Members.
+        let res = WorkspaceBmc::add_member(&ctx_other, &mm, id, ctx_other.user_id()).await;
+        assert!(
+            matches!(res, Err(Error::AccessDenied { .. })),
+            "AccessDenied not matching: {res:?}"
+        );
+        WorkspaceBmc::add_member(&ctx, &mm, id, ctx_other.user_id()).await?;
+        // Already a member.
+        WorkspaceBmc::add_member(&ctx, &mm, id, ctx_other.user_id()).await?;
+        let members = WorkspaceBmc::list_members(&ctx_other, &mm, id).await?;
+        let members: Vec<(&str, WorkspaceRole)> = members
+            .iter()
+            .map(|member| (member.username.as_str(), member.role))
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ("demo1", WorkspaceRole::Owner),
+                ("demo2", WorkspaceRole::Member)
+            ]
+        );
+
+        // Only the owners manage the members.
+        let res = WorkspaceBmc::remove_member(&ctx_other, &mm, id, ctx.user_id()).await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::AccessDenied {
+                    entity: "workspaces",
+                    ..
+                })
+            ),
+            "AccessDenied not matching: {res:?}"
+        );
+        let res = WorkspaceBmc::remove_member(&ctx, &mm, id, ctx.user_id()).await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::AccessDenied {
+                    entity: "workspace_members",
+                    ..
+                })
+            ),
+            "AccessDenied not matching: {res:?}"
+        );
+        WorkspaceBmc::remove_member(&ctx, &mm, id, ctx_other.user_id()).await?;
+        let res = WorkspaceBmc::list_members(&ctx_other, &mm, id).await;
+        assert!(
+            matches!(res, Err(Error::AccessDenied { .. })),
+            "AccessDenied not matching: {res:?}"
+        );
+        let res = WorkspaceBmc::add_member(&ctx, &mm, id, 999_999).await;
+        assert!(
+            matches!(
+                res,
+                Err(Error::EntityNotFound {
+                    entity: "users",
+                    ..
+                })
+            ),
+            "EntityNotFound not matching: {res:?}"
+        );
+
+        // Back to the seeded workspace, for the other tests.
+        WorkspaceBmc::set_default(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID).await?;
+        let mut db = mm.db(&Ctx::root_ctx()).await?;
+        sqlx::query("DELETE FROM workspaces WHERE id = $1")
+            .bind(id)
+            .execute(&mut *db)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+use serde::Serialize;
+
 pub type Result<T> = core::result::Result<T, Error>;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum Error {
     DateFailParse(String),
     FailToB64Decode,
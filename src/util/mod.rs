@@ -19,6 +19,11 @@ pub fn now_utc_plus_sec_str(sec: f64) -> String {
     format_time(future)
 }
 
+/// Duration from now until the `time`, zero if past.
+pub fn duration_until(time: OffsetDateTime) -> std::time::Duration {
+    (time - now_utc()).try_into().unwrap_or_default()
+}
+
 pub fn parse_utc(moment: &str) -> Result<OffsetDateTime> {
     OffsetDateTime::parse(moment, &Rfc3339).map_err(|_| Error::DateFailParse(moment.to_string()))
 }
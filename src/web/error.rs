@@ -7,6 +7,7 @@ use axum::{
 use serde::Serialize;
 use serde_with::serde_as;
 
+use crate::model::validation::FieldError;
 use crate::{crypt, model, web};
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -19,9 +20,12 @@ pub enum Error {
     LoginFailedUserHasNoPassword { user_id: i64 },
     LoginFailedPasswordIncorrect { user_id: i64 },
 
+    RpcRequestParsing(String),
+    RpcRequestInvalid(String),
+    RpcBatchTooLarge { size: usize, max_size: usize },
     RpcMethodUnknown(String),
     RpcMissingParams { method: String },
-    RpcFailJsonParams { method: String },
+    RpcFailJsonParams { method: String, cause: String },
 
     CtxExt(web::mw_auth::CtxExtError),
     Model(model::Error),
@@ -86,7 +90,81 @@ impl Error {
                 StatusCode::BAD_REQUEST,
                 ClientError::ENTITY_NOT_FOUND { entity, id: *id },
             ),
+            Model(model::Error::AccessDenied { entity, id }) => (
+                StatusCode::FORBIDDEN,
+                ClientError::ACCESS_DENIED { entity, id: *id },
+            ),
+            Model(model::Error::ValidationFailed { entity, errors }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::VALIDATION_FAILED {
+                    entity,
+                    errors: errors.clone(),
+                },
+            ),
+            Model(model::Error::WorkspaceRequired { entity }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::WORKSPACE_REQUIRED { entity },
+            ),
+            Model(model::Error::Conflict {
+                entity,
+                id,
+                current,
+            }) => (
+                StatusCode::CONFLICT,
+                ClientError::CONFLICT {
+                    entity,
+                    id: *id,
+                    current: current.clone(),
+                },
+            ),
+            Model(model::Error::EntityInUse { entity, id, by }) => (
+                StatusCode::CONFLICT,
+                ClientError::ENTITY_IN_USE {
+                    entity,
+                    id: *id,
+                    by,
+                },
+            ),
+            Model(model::Error::IdempotencyKeyReused { key }) => (
+                StatusCode::CONFLICT,
+                ClientError::IDEMPOTENCY_KEY_REUSED { key: key.clone() },
+            ),
+            Model(model::Error::IdempotencyKeyInProgress { key }) => (
+                StatusCode::CONFLICT,
+                ClientError::IDEMPOTENCY_KEY_IN_PROGRESS { key: key.clone() },
+            ),
             CtxExt(_) => (StatusCode::FORBIDDEN, ClientError::CTX_ERROR),
+
+            // -- Rpc
+            RpcRequestParsing(cause) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_PARSING(cause.to_string()),
+            ),
+            RpcRequestInvalid(cause) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_INVALID(cause.to_string()),
+            ),
+            RpcBatchTooLarge { size, max_size } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_INVALID(format!(
+                    "Batch of {size} calls exceeds the max size of {max_size}"
+                )),
+            ),
+            RpcMethodUnknown(method) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_METHOD_UNKNOWN(method.to_string()),
+            ),
+            RpcMissingParams { method } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_PARAMS_INVALID(format!("Params missing for method '{method}'")),
+            ),
+            RpcFailJsonParams { method, cause } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_PARAMS_INVALID(format!(
+                    "Params invalid for method '{method}': {cause}"
+                )),
+            ),
+
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ClientError::SERVICE_ERROR,
@@ -97,11 +175,77 @@ impl Error {
 
 #[derive(Debug, Serialize, strum_macros::AsRefStr)]
 #[serde(tag = "message", content = "detail")]
-#[allow(non_camel_case_types)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 pub enum ClientError {
-    ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
+    ENTITY_NOT_FOUND {
+        entity: &'static str,
+        id: i64,
+    },
+    ACCESS_DENIED {
+        entity: &'static str,
+        id: i64,
+    },
+    VALIDATION_FAILED {
+        entity: &'static str,
+        errors: Vec<FieldError>,
+    },
+    WORKSPACE_REQUIRED {
+        entity: &'static str,
+    },
+    CONFLICT {
+        entity: &'static str,
+        id: i64,
+        current: Option<serde_json::Value>,
+    },
+    ENTITY_IN_USE {
+        entity: &'static str,
+        id: i64,
+        by: &'static str,
+    },
+    IDEMPOTENCY_KEY_REUSED {
+        key: String,
+    },
+    IDEMPOTENCY_KEY_IN_PROGRESS {
+        key: String,
+    },
     LOGIN_FAILED,
-    NO_AUTH,
     CTX_ERROR,
+
+    RPC_REQUEST_PARSING(String),
+    RPC_REQUEST_INVALID(String),
+    RPC_REQUEST_METHOD_UNKNOWN(String),
+    RPC_PARAMS_INVALID(String),
+
     SERVICE_ERROR,
 }
+
+impl ClientError {
+    /// JSON-RPC 2.0 error code for this client error.
+    ///
+    /// Spec codes are used for protocol failures, and the
+    /// implementation-defined `-32000..-32099` range for app errors.
+    pub fn rpc_code(&self) -> i64 {
+        use ClientError::*;
+
+        match self {
+            // -- JSON-RPC spec codes
+            RPC_REQUEST_PARSING(_) => -32700,
+            RPC_REQUEST_INVALID(_) => -32600,
+            RPC_REQUEST_METHOD_UNKNOWN(_) => -32601,
+            RPC_PARAMS_INVALID(_) => -32602,
+            SERVICE_ERROR => -32603,
+
+            // -- App codes
+            CTX_ERROR => -32001,
+            LOGIN_FAILED => -32002,
+            ACCESS_DENIED { .. } => -32003,
+            ENTITY_NOT_FOUND { .. } => -32004,
+            VALIDATION_FAILED { .. } => -32005,
+            CONFLICT { .. } => -32009,
+            IDEMPOTENCY_KEY_REUSED { .. } => -32010,
+            IDEMPOTENCY_KEY_IN_PROGRESS { .. } => -32011,
+            WORKSPACE_REQUIRED { .. } => -32012,
+            ENTITY_IN_USE { .. } => -32013,
+        }
+    }
+}
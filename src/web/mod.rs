@@ -1,17 +1,25 @@
 mod error;
 pub mod mw_auth;
+pub mod routes_events;
 pub mod routes_login;
 pub mod routes_static;
+pub mod routes_workspace;
 pub mod rpc;
 
-pub use error::{Error, Result};
+pub use error::{ClientError, Error, Result};
 use tower_cookies::{Cookie, Cookies};
 
-use crate::crypt::token::generate_web_token;
+use crate::crypt::token::{generate_web_token, Token};
 
 pub const AUTH_TOKEN: &str = "auth-token";
 
-fn set_token_cookie(cookies: &Cookies, user: &str, salt: &str) -> Result<()> {
+/// Request header selecting the ctx workspace, over the one of the session.
+pub const WORKSPACE_HEADER: &str = "x-workspace-id";
+
+/// Workspace of the session, see `routes_workspace`.
+pub const WORKSPACE_COOKIE: &str = "workspace-id";
+
+fn set_token_cookie(cookies: &Cookies, user: &str, salt: &str) -> Result<Token> {
     let token = generate_web_token(user, salt)?;
 
     let mut cookie = Cookie::new(AUTH_TOKEN, token.to_string());
@@ -20,7 +28,7 @@ fn set_token_cookie(cookies: &Cookies, user: &str, salt: &str) -> Result<()> {
 
     cookies.add(cookie);
 
-    Ok(())
+    Ok(token)
 }
 
 fn remove_token_cookie(cookies: &Cookies) -> Result<()> {
@@ -31,3 +39,18 @@ fn remove_token_cookie(cookies: &Cookies) -> Result<()> {
 
     Ok(())
 }
+
+fn set_workspace_cookie(cookies: &Cookies, workspace_id: i64) {
+    let mut cookie = Cookie::new(WORKSPACE_COOKIE, workspace_id.to_string());
+    cookie.set_http_only(true);
+    cookie.set_path("/");
+
+    cookies.add(cookie);
+}
+
+fn remove_workspace_cookie(cookies: &Cookies) {
+    let mut cookie = Cookie::named(WORKSPACE_COOKIE);
+    cookie.set_path("/");
+
+    cookies.remove(cookie);
+}
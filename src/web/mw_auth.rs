@@ -1,17 +1,20 @@
 use crate::crypt::token::{validate_web_token, Token};
 use crate::ctx::Ctx;
 use crate::model::user::{UserBmc, UserForAuth};
+use crate::model::workspace::WorkspaceBmc;
 use crate::model::ModelManager;
-use crate::web::{set_token_cookie, AUTH_TOKEN};
+use crate::util::parse_utc;
+use crate::web::{set_token_cookie, AUTH_TOKEN, WORKSPACE_COOKIE, WORKSPACE_HEADER};
 use crate::web::{Error, Result};
 use async_trait::async_trait;
 use axum::extract::FromRequestParts;
 use axum::extract::State;
 use axum::http::request::Parts;
-use axum::http::Request;
+use axum::http::{HeaderMap, Request};
 use axum::middleware::Next;
 use axum::response::Response;
 use serde::Serialize;
+use time::OffsetDateTime;
 use tower_cookies::{Cookie, Cookies};
 use tracing::info;
 
@@ -35,9 +38,17 @@ pub async fn mw_ctx_resolve<B>(
 ) -> Result<Response> {
     info!("->> {:<12} - mw_ctx_resolve", "MIDDLEWARE");
 
-    let ctx_ext_result = _ctx_resolve(mm, &cookies).await;
-
-    if ctx_ext_result.is_err() && !matches!(ctx_ext_result, Err(CtxExtError::TokenNotInCookie)) {
+    let ctx_ext_result = _ctx_resolve(mm, &cookies, req.headers()).await;
+
+    // Only an invalid token invalidates the session, not a transient model
+    // access failure nor a wrong workspace header.
+    if matches!(
+        ctx_ext_result,
+        Err(CtxExtError::TokenWrongFormat
+            | CtxExtError::UserNotFound
+            | CtxExtError::FailValidate
+            | CtxExtError::CtxCreateFail(_))
+    ) {
         cookies.remove(Cookie::named(AUTH_TOKEN));
     }
 
@@ -46,7 +57,11 @@ pub async fn mw_ctx_resolve<B>(
     Ok(next.run(req).await)
 }
 
-async fn _ctx_resolve(mm: State<ModelManager>, cookies: &Cookies) -> CtxExtResult {
+async fn _ctx_resolve(
+    mm: State<ModelManager>,
+    cookies: &Cookies,
+    headers: &HeaderMap,
+) -> CtxExtResult {
     let token = cookies
         .get(AUTH_TOKEN)
         .map(|c| c.value().to_string())
@@ -62,16 +77,81 @@ async fn _ctx_resolve(mm: State<ModelManager>, cookies: &Cookies) -> CtxExtResul
     validate_web_token(&token, &user.token_salt.to_string())
         .map_err(|_| CtxExtError::FailValidate)?;
 
-    set_token_cookie(&cookies, &user.username, &user.token_salt.to_string())
+    let token = set_token_cookie(cookies, &user.username, &user.token_salt.to_string())
         .map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+    let session_exp = parse_utc(&token.exp).map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+
+    let ctx = Ctx::new(user.id).map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))?;
+    let workspace_id = resolve_workspace(&ctx, &mm, &user, cookies, headers).await?;
+
+    Ok(CtxW(ctx.with_workspace_id(workspace_id), session_exp))
+}
+
+/// Workspace of the `WORKSPACE_HEADER`, or else the one of the session
+/// (`WORKSPACE_COOKIE`), or else the default one of the user.
+///
+/// The user must be a member of the header workspace, while a session or
+/// default workspace it is no more a member of is ignored.
+async fn resolve_workspace(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    user: &UserForAuth,
+    cookies: &Cookies,
+    headers: &HeaderMap,
+) -> core::result::Result<Option<i64>, CtxExtError> {
+    let header_workspace_id = headers
+        .get(WORKSPACE_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<i64>().ok())
+                .ok_or(CtxExtError::WorkspaceWrongFormat)
+        })
+        .transpose()?;
+
+    if let Some(workspace_id) = header_workspace_id {
+        return match is_member(ctx, mm, workspace_id).await? {
+            true => Ok(Some(workspace_id)),
+            false => Err(CtxExtError::WorkspaceNotMember),
+        };
+    }
 
-    Ctx::new(user.id)
-        .map(CtxW)
-        .map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))
+    let session_workspace_id = cookies
+        .get(WORKSPACE_COOKIE)
+        .and_then(|c| c.value().parse::<i64>().ok());
+
+    for workspace_id in [session_workspace_id, user.workspace_id]
+        .into_iter()
+        .flatten()
+    {
+        if is_member(ctx, mm, workspace_id).await? {
+            return Ok(Some(workspace_id));
+        }
+    }
+
+    Ok(None)
+}
+
+async fn is_member(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    workspace_id: i64,
+) -> core::result::Result<bool, CtxExtError> {
+    let role = WorkspaceBmc::role(ctx, mm, workspace_id)
+        .await
+        .map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?;
+
+    Ok(role.is_some())
 }
 
 #[derive(Debug, Clone)]
-pub struct CtxW(pub Ctx);
+pub struct CtxW(
+    pub Ctx,
+    /// Expiration of the session, the auth token refreshed for the request,
+    /// which the long lived connections (ws, sse) end at.
+    pub OffsetDateTime,
+);
 
 #[async_trait]
 impl<S: Send + Sync> FromRequestParts<S> for CtxW {
@@ -101,6 +181,9 @@ pub enum CtxExtError {
     FailValidate,
     CannotSetTokenCookie,
 
+    WorkspaceWrongFormat,
+    WorkspaceNotMember,
+
     CtxNotInRequestExt,
     CtxCreateFail(String),
 }
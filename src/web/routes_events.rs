@@ -0,0 +1,72 @@
+use std::convert::Infallible;
+
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::Router;
+use futures::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::info;
+
+use crate::model::event::ModelEvent;
+use crate::model::ModelManager;
+use crate::util::duration_until;
+use crate::web::mw_auth::CtxW;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/events", get(sse_events_handler))
+        .with_state(mm)
+}
+
+/// Server-Sent Events feed of the model changes the ctx user may see.
+///
+/// Each event carries the model event id, so a client reconnecting with a
+/// `Last-Event-ID` header first gets the buffered events it missed.
+/// The stream ends when the client lags behind, so it reconnects and
+/// resumes from the buffer. It also ends when the session expires, the
+/// ctx being resolved once, so the reconnection is authenticated again.
+async fn sse_events_handler(
+    State(mm): State<ModelManager>,
+    ctx_w: CtxW,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let CtxW(ctx, session_exp) = ctx_w;
+
+    let last_id = headers
+        .get(LAST_EVENT_ID)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    info!("->> {:<12} - sse_events - last_id: {last_id:?}", "HANDLER");
+
+    let (missed, rx) = mm.subscribe_events_since(last_id);
+
+    let stream = stream::iter(missed)
+        .chain(live_events(rx))
+        .filter(move |event| futures::future::ready(event.is_visible_to(&ctx)))
+        .map(|event| Ok(sse_event(&event)))
+        .take_until(tokio::time::sleep(duration_until(session_exp)));
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn live_events(rx: broadcast::Receiver<ModelEvent>) -> impl Stream<Item = ModelEvent> {
+    stream::unfold(rx, |mut rx| async move {
+        match rx.recv().await {
+            Ok(event) => Some((event, rx)),
+            Err(RecvError::Lagged(_) | RecvError::Closed) => None,
+        }
+    })
+}
+
+fn sse_event(event: &ModelEvent) -> Event {
+    Event::default()
+        .id(event.id.to_string())
+        .json_data(event)
+        // ModelEvent serialization cannot fail.
+        .unwrap_or_default()
+}
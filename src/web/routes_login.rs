@@ -8,7 +8,7 @@ use crate::crypt::{pwd, EncryptContent};
 use crate::ctx::Ctx;
 use crate::model::user::{UserBmc, UserForLogin};
 use crate::model::ModelManager;
-use crate::web::{self, remove_token_cookie, Error, Result};
+use crate::web::{self, remove_token_cookie, remove_workspace_cookie, Error, Result};
 
 pub fn routes(mm: ModelManager) -> Router {
     Router::new()
@@ -78,6 +78,7 @@ async fn api_logout_handler(
 
     if logout {
         remove_token_cookie(&cookies)?;
+        remove_workspace_cookie(&cookies);
     }
 
     let body = Json(json!({
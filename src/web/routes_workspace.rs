@@ -0,0 +1,45 @@
+use axum::extract::State;
+use axum::{routing::post, Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tower_cookies::Cookies;
+
+use crate::model::workspace::WorkspaceBmc;
+use crate::model::ModelManager;
+use crate::web::mw_auth::CtxW;
+use crate::web::{self, Result};
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/workspace/switch", post(api_switch_workspace_handler))
+        .with_state(mm)
+}
+
+#[derive(Debug, Deserialize)]
+struct SwitchWorkspacePayload {
+    id: i64,
+}
+
+/// Makes the workspace the current one of the session, used by its next
+/// requests without the `WORKSPACE_HEADER`. The other sessions of the user
+/// keep theirs.
+async fn api_switch_workspace_handler(
+    State(mm): State<ModelManager>,
+    ctx_w: CtxW,
+    cookies: Cookies,
+    Json(payload): Json<SwitchWorkspacePayload>,
+) -> Result<Json<Value>> {
+    let SwitchWorkspacePayload { id } = payload;
+    let ctx = ctx_w.0.with_workspace_id(Some(id));
+
+    // Checks the membership.
+    let workspace = WorkspaceBmc::get(&ctx, &mm, id).await?;
+
+    web::set_workspace_cookie(&cookies, id);
+
+    let body = Json(json!({
+        "result": workspace,
+    }));
+
+    Ok(body)
+}
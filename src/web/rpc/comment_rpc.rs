@@ -0,0 +1,102 @@
+use crate::ctx::Ctx;
+use crate::model::comment::{Comment, CommentBmc, CommentForCreate, CommentForUpdate};
+use crate::model::filter::Filter;
+use crate::model::list_options::{ListOptions, Page};
+use crate::model::ModelManager;
+use crate::web::rpc::{
+    rpc_router, ParamsForCreate, ParamsForUpdateUnversioned, ParamsIded, RpcRouter,
+};
+use crate::web::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub fn rpc_router() -> RpcRouter {
+    rpc_router!(
+        create_comment,
+        list_comments,
+        update_comment,
+        delete_comment
+    )
+}
+
+/// Params of `list_comments`, as `ParamsList` for the comments of the task.
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsListComments {
+    task_id: i64,
+    filters: Option<Vec<Filter>>,
+    list_options: Option<ListOptions>,
+}
+
+/// Creates a comment of the ctx user on a task of the ctx workspace, of
+/// any of its members.
+pub async fn create_comment(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<CommentForCreate>,
+) -> Result<Comment> {
+    let ParamsForCreate { data } = params;
+
+    mm.transaction(|mm| async move {
+        let id = CommentBmc::create(&ctx, &mm, data).await?;
+        let comment = CommentBmc::get(&ctx, &mm, id).await?;
+
+        Ok(comment)
+    })
+    .await
+}
+
+/// Lists the comments of the task, the replies linked by their `parent_id`.
+/// The deleted comments are listed as tombstones, with an empty `content`,
+/// and `include_deleted` is ignored.
+pub async fn list_comments(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsListComments,
+) -> Result<Page<Comment>> {
+    let ParamsListComments {
+        task_id,
+        filters,
+        list_options,
+    } = params;
+
+    let comments = CommentBmc::list(
+        &ctx,
+        &mm,
+        task_id,
+        filters.unwrap_or_default(),
+        list_options.unwrap_or_default(),
+    )
+    .await?;
+
+    Ok(comments)
+}
+
+/// Edits the content of a comment of the ctx user.
+pub async fn update_comment(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdateUnversioned<CommentForUpdate>,
+) -> Result<Comment> {
+    let ParamsForUpdateUnversioned { id, data } = params;
+
+    mm.transaction(|mm| async move {
+        CommentBmc::update(&ctx, &mm, id, data).await?;
+        let comment = CommentBmc::get(&ctx, &mm, id).await?;
+
+        Ok(comment)
+    })
+    .await
+}
+
+/// Soft deletes a comment of the ctx user, returned as its tombstone.
+pub async fn delete_comment(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Comment> {
+    let ParamsIded { id } = params;
+
+    mm.transaction(|mm| async move {
+        CommentBmc::delete(&ctx, &mm, id).await?;
+        let comment = CommentBmc::get_include_deleted(&ctx, &mm, id).await?;
+
+        Ok(comment)
+    })
+    .await
+}
@@ -0,0 +1,74 @@
+use crate::ctx::Ctx;
+use crate::model::label::{Label, LabelBmc, LabelForCreate, LabelForUpdate};
+use crate::model::list_options::Page;
+use crate::model::ModelManager;
+use crate::web::rpc::{
+    rpc_router, ParamsForCreate, ParamsForUpdateUnversioned, ParamsIded, ParamsList, RpcRouter,
+};
+use crate::web::Result;
+
+pub fn rpc_router() -> RpcRouter {
+    rpc_router!(create_label, list_labels, update_label, delete_label)
+}
+
+pub async fn create_label(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<LabelForCreate>,
+) -> Result<Label> {
+    let ParamsForCreate { data } = params;
+
+    mm.transaction(|mm| async move {
+        let id = LabelBmc::create(&ctx, &mm, data).await?;
+        let label = LabelBmc::get(&ctx, &mm, id).await?;
+
+        Ok(label)
+    })
+    .await
+}
+
+pub async fn list_labels(ctx: Ctx, mm: ModelManager, params: ParamsList) -> Result<Page<Label>> {
+    let ParamsList {
+        filters,
+        list_options,
+    } = params;
+
+    let labels = LabelBmc::list(
+        &ctx,
+        &mm,
+        filters.unwrap_or_default(),
+        list_options.unwrap_or_default(),
+    )
+    .await?;
+
+    Ok(labels)
+}
+
+pub async fn update_label(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdateUnversioned<LabelForUpdate>,
+) -> Result<Label> {
+    let ParamsForUpdateUnversioned { id, data } = params;
+
+    mm.transaction(|mm| async move {
+        LabelBmc::update(&ctx, &mm, id, data).await?;
+        let label = LabelBmc::get(&ctx, &mm, id).await?;
+
+        Ok(label)
+    })
+    .await
+}
+
+/// Deletes the label, detached from its tasks. Returned as it was before.
+pub async fn delete_label(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Label> {
+    let ParamsIded { id } = params;
+
+    mm.transaction(|mm| async move {
+        let label = LabelBmc::get(&ctx, &mm, id).await?;
+        LabelBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(label)
+    })
+    .await
+}
@@ -1,99 +1,718 @@
+pub mod comment_rpc;
+pub mod label_rpc;
+mod openrpc;
+pub mod project_rpc;
+mod response;
+mod router;
 pub mod task_rpc;
+pub mod workspace_rpc;
+mod ws;
+
+use std::sync::Arc;
 
 use axum::{
+    body::Bytes,
     extract::State,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{get, post},
     Json, Router,
 };
-use serde::Deserialize;
-use serde_json::{from_value, json, to_value, Value};
-use task_rpc::{create_task, delete_task, list_tasks, update_task};
+use futures::stream::{self, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+use serde_json::{from_value, Value};
 
 use crate::{
+    config,
     ctx::Ctx,
-    model::ModelManager,
+    model::filter::Filter,
+    model::idempotency::{IdempotencyBmc, IdempotencyClaim},
+    model::list_options::ListOptions,
+    model::{ModelManager, DB_MAX_CONNECTIONS},
     web::{Error, Result},
 };
 
 use crate::web::mw_auth::CtxW;
 
+use openrpc::RPC_DISCOVER_METHOD;
+pub use response::{BulkItemResult, RpcPayloadResponse, RpcResponse};
+pub(crate) use router::rpc_router;
+pub use router::{RpcHandler, RpcRouter};
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Http header of the idempotency key of a single (non batch) rpc call.
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+const IDEMPOTENCY_KEY_MAX_LEN: usize = 255;
+
+/// JSON-RPC 2.0 request object.
+///
+/// `id` is `None` when the member is absent, and `Some(Value::Null)` when
+/// it was sent as `null`.
+///
+/// `idempotency_key` is an extension member. When present, a retry of the
+/// call with the same key gets the stored result instead of executing the
+/// method again (see `IdempotencyBmc`). Ignored for the read methods.
 #[derive(Deserialize)]
-struct RpcRequest {
-    id: Option<String>,
+pub struct RpcRequest {
+    jsonrpc: String,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    id: Option<Value>,
     method: String,
-    params: Option<serde_json::Value>,
+    params: Option<Value>,
+    idempotency_key: Option<String>,
 }
 
-#[derive(Deserialize)]
+/// Rpc call information inserted in the response extensions (one per
+/// call), so the response mapper can log each call of a batch, and knows
+/// the body is already JSON-RPC.
+#[derive(Debug, Clone)]
+pub struct RpcInfo {
+    pub id: Option<Value>,
+    pub method: Option<String>,
+    pub error: Option<Arc<Error>>,
+}
+
+#[derive(Deserialize, JsonSchema)]
 pub struct ParamsForCreate<D> {
     data: D,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct ParamsForUpdate<D> {
     id: i64,
     data: D,
+    /// Expected entity version, the update fails with a `CONFLICT` if the
+    /// entity was updated since.
+    version: Option<i64>,
 }
 
-#[derive(Deserialize)]
+/// `ParamsForUpdate` of the entities without version, a `version` is
+/// rejected rather than ignored.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ParamsForUpdateUnversioned<D> {
+    id: i64,
+    data: D,
+}
+
+#[derive(Deserialize, JsonSchema)]
 pub struct ParamsIded {
     id: i64,
 }
 
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsForCreateMany<D> {
+    data: Vec<D>,
+}
+
+/// Params of the bulk update methods, the entities are selected either by
+/// `ids` or by `filters` (combined with AND).
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsForUpdateMany<D> {
+    ids: Option<Vec<i64>>,
+    filters: Option<Vec<Filter>>,
+    data: D,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsIdeds {
+    ids: Vec<i64>,
+}
+
+/// Params of the list methods, `filters` are combined with AND.
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsList {
+    filters: Option<Vec<Filter>>,
+    list_options: Option<ListOptions>,
+}
+
+#[derive(Clone)]
+pub struct RpcState {
+    mm: ModelManager,
+    rpc_router: Arc<RpcRouter>,
+}
+
+/// Router of all the rpc methods, merged from each rpc module.
+fn all_rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .extend(comment_rpc::rpc_router())
+        .extend(label_rpc::rpc_router())
+        .extend(project_rpc::rpc_router())
+        .extend(task_rpc::rpc_router())
+        .extend(workspace_rpc::rpc_router())
+}
+
 pub fn routes(mm: ModelManager) -> Router {
+    let rpc_state = RpcState {
+        mm,
+        rpc_router: Arc::new(all_rpc_router()),
+    };
+
     Router::new()
         .route("/rpc", post(rpc_handler))
-        .with_state(mm)
+        .route("/openrpc.json", get(openrpc_handler))
+        .route("/ws", get(ws::ws_handler))
+        .with_state(rpc_state)
 }
 
+/// OpenRPC document of the rpc methods, same as the `rpc.discover` result.
+async fn openrpc_handler(State(rpc_state): State<RpcState>) -> Json<Value> {
+    Json(rpc_state.rpc_router.openrpc_document())
+}
+
+/// Http entry point of the JSON-RPC endpoint.
+///
+/// A payload made only of notifications gets a `204 No Content`.
+/// Rpc level failures are answered with HTTP 200 and a JSON-RPC error
+/// object.
+///
+/// The `Idempotency-Key` header applies to a single call payload, batch
+/// calls must use their `idempotency_key` member.
 pub async fn rpc_handler(
-    State(mm): State<ModelManager>,
+    State(rpc_state): State<RpcState>,
     ctx_w: CtxW,
-    Json(rpc_req): Json<RpcRequest>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> Response {
-    _rpc_handler(mm, ctx_w.0, rpc_req).await.into_response()
+    let RpcState { mm, rpc_router } = rpc_state;
+
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+
+    let (rpc_infos, rpc_response) =
+        exec_rpc_payload(&rpc_router, mm, ctx_w.0, &body, idempotency_key).await;
+
+    let mut response = match rpc_response {
+        Some(rpc_response) => Json(rpc_response).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    };
+
+    response.extensions_mut().insert(rpc_infos);
+
+    response
 }
 
-macro_rules! exec_rpc_fn {
-    ($rpc_fn:expr, $ctx:expr, $mm:expr) => {
-        $rpc_fn($ctx, $mm).await.map(to_value)??
+/// Executes a JSON-RPC payload, a single request object or a batch (json
+/// array) of them.
+///
+/// Notifications are executed but not answered, so there is no response
+/// when the payload is made only of notifications.
+///
+/// `idempotency_key` is the key from the transport (e.g., http header),
+/// used when the payload is a single call without its own key.
+pub async fn exec_rpc_payload(
+    rpc_router: &RpcRouter,
+    mm: ModelManager,
+    ctx: Ctx,
+    payload: &[u8],
+    idempotency_key: Option<String>,
+) -> (Vec<RpcInfo>, Option<RpcPayloadResponse>) {
+    let value = match serde_json::from_slice::<Value>(payload) {
+        Ok(value) => value,
+        Err(ex) => {
+            let web_error = Error::RpcRequestParsing(ex.to_string());
+            let (rpc_info, rpc_response) = rpc_error(None, None, web_error);
+            return (
+                vec![rpc_info],
+                Some(RpcPayloadResponse::Single(rpc_response)),
+            );
+        }
+    };
+
+    let Value::Array(values) = value else {
+        let (rpc_info, rpc_response) = exec_rpc(rpc_router, mm, ctx, value, idempotency_key).await;
+        return (vec![rpc_info], rpc_response.map(RpcPayloadResponse::Single));
     };
 
-    ($rpc_fn:expr, $ctx:expr, $mm:expr, $rpc_param:expr) => {{
-        let rpc_fn_name = stringify!($rpc_fn);
+    let batch_check = match idempotency_key {
+        Some(_) => Err(Error::RpcRequestInvalid(
+            "Idempotency-Key header not supported for batch, use the call idempotency_key"
+                .to_string(),
+        )),
+        None => check_batch_size(values.len()),
+    };
+
+    if let Err(web_error) = batch_check {
+        let (rpc_info, rpc_response) = rpc_error(None, None, web_error);
+        return (
+            vec![rpc_info],
+            Some(RpcPayloadResponse::Single(rpc_response)),
+        );
+    }
+
+    let concurrent = config().RPC_BATCH_CONCURRENT;
+    let (rpc_infos, rpc_responses) = exec_rpc_batch(rpc_router, mm, ctx, values, concurrent).await;
+    let rpc_responses: Vec<RpcResponse> = rpc_responses.into_iter().flatten().collect();
+
+    let rpc_response =
+        (!rpc_responses.is_empty()).then_some(RpcPayloadResponse::Batch(rpc_responses));
 
-        let params = $rpc_param.ok_or(Error::RpcMissingParams {
-            method: rpc_fn_name.to_string(),
-        })?;
+    (rpc_infos, rpc_response)
+}
+
+fn check_batch_size(size: usize) -> Result<()> {
+    let max_size = config().RPC_BATCH_MAX_SIZE;
+
+    if size == 0 {
+        return Err(Error::RpcRequestInvalid(
+            "Batch must not be empty".to_string(),
+        ));
+    }
+
+    if size > max_size {
+        return Err(Error::RpcBatchTooLarge { size, max_size });
+    }
+
+    Ok(())
+}
+
+/// Executes each call of a batch, sequentially or `concurrent`ly (the
+/// `RPC_BATCH_CONCURRENT` config), with at most one call per database
+/// connection at once, so the others wait rather than time out on the pool.
+/// Responses are returned in the order of the calls.
+async fn exec_rpc_batch(
+    rpc_router: &RpcRouter,
+    mm: ModelManager,
+    ctx: Ctx,
+    values: Vec<Value>,
+    concurrent: bool,
+) -> (Vec<RpcInfo>, Vec<Option<RpcResponse>>) {
+    if concurrent {
+        let calls = values
+            .into_iter()
+            .map(|value| exec_rpc(rpc_router, mm.clone(), ctx.clone(), value, None));
 
-        let params = from_value(params).map_err(|_| Error::RpcFailJsonParams {
-            method: rpc_fn_name.to_string(),
-        })?;
+        stream::iter(calls)
+            .buffered(DB_MAX_CONNECTIONS as usize)
+            .unzip()
+            .await
+    } else {
+        let mut rpc_infos = Vec::with_capacity(values.len());
+        let mut rpc_responses = Vec::with_capacity(values.len());
 
-        $rpc_fn($ctx, $mm, params).await.map(to_value)??
-    }};
+        for value in values {
+            let (rpc_info, rpc_response) =
+                exec_rpc(rpc_router, mm.clone(), ctx.clone(), value, None).await;
+            rpc_infos.push(rpc_info);
+            rpc_responses.push(rpc_response);
+        }
+
+        (rpc_infos, rpc_responses)
+    }
 }
 
-pub async fn _rpc_handler(mm: ModelManager, ctx: Ctx, rpc_req: RpcRequest) -> Result<Json<Value>> {
+/// Parses and executes one rpc call.
+///
+/// Returns no response for notifications (requests without an `id`).
+/// Invalid requests are always answered, since they cannot be told apart
+/// from a notification.
+async fn exec_rpc(
+    rpc_router: &RpcRouter,
+    mm: ModelManager,
+    ctx: Ctx,
+    value: Value,
+    idempotency_key: Option<String>,
+) -> (RpcInfo, Option<RpcResponse>) {
+    let mut rpc_req = match parse_rpc_request(value) {
+        Ok(rpc_req) => rpc_req,
+        Err((id, web_error)) => {
+            let (rpc_info, rpc_response) = rpc_error(id, None, web_error);
+            return (rpc_info, Some(rpc_response));
+        }
+    };
+
+    if rpc_req.idempotency_key.is_none() {
+        rpc_req.idempotency_key = idempotency_key;
+    }
+
+    let is_notification = rpc_req.id.is_none();
+    let id = rpc_req.id.clone();
+    let method = rpc_req.method.clone();
+
+    let (rpc_info, rpc_response) = match _rpc_handler(rpc_router, mm, ctx, rpc_req).await {
+        Ok(result) => {
+            let rpc_info = RpcInfo {
+                id: id.clone(),
+                method: Some(method),
+                error: None,
+            };
+            (rpc_info, RpcResponse::result(id, result))
+        }
+        Err(web_error) => rpc_error(id, Some(method), web_error),
+    };
+
+    (rpc_info, (!is_notification).then_some(rpc_response))
+}
+
+fn rpc_error(
+    id: Option<Value>,
+    method: Option<String>,
+    web_error: Error,
+) -> (RpcInfo, RpcResponse) {
+    let rpc_response = RpcResponse::error(id.clone(), &web_error);
+    let rpc_info = RpcInfo {
+        id,
+        method,
+        error: Some(Arc::new(web_error)),
+    };
+
+    (rpc_info, rpc_response)
+}
+
+/// Validates a json value as a JSON-RPC 2.0 request object.
+///
+/// On failure, returns the request id when it could be read, so it can be
+/// echoed back in the error response.
+fn parse_rpc_request(value: Value) -> core::result::Result<RpcRequest, (Option<Value>, Error)> {
+    let id = value
+        .get("id")
+        .filter(|id| id.is_string() || id.is_number())
+        .cloned();
+
+    if !value.is_object() {
+        return Err((
+            id,
+            Error::RpcRequestInvalid("Request must be a json object".to_string()),
+        ));
+    }
+
+    let rpc_req: RpcRequest =
+        from_value(value).map_err(|ex| (id.clone(), Error::RpcRequestInvalid(ex.to_string())))?;
+
+    if let Some(rpc_id) = &rpc_req.id {
+        if !(rpc_id.is_string() || rpc_id.is_number() || rpc_id.is_null()) {
+            return Err((
+                None,
+                Error::RpcRequestInvalid("id must be a string, number or null".to_string()),
+            ));
+        }
+    }
+
+    if let Some(key) = &rpc_req.idempotency_key {
+        if key.is_empty() || key.chars().count() > IDEMPOTENCY_KEY_MAX_LEN {
+            return Err((
+                id,
+                Error::RpcRequestInvalid(format!(
+                    "idempotency_key must be 1 to {IDEMPOTENCY_KEY_MAX_LEN} characters"
+                )),
+            ));
+        }
+    }
+
+    if rpc_req.jsonrpc != JSONRPC_VERSION {
+        return Err((
+            id,
+            Error::RpcRequestInvalid(format!(
+                "jsonrpc must be \"{JSONRPC_VERSION}\", was \"{}\"",
+                rpc_req.jsonrpc
+            )),
+        ));
+    }
+
+    Ok(rpc_req)
+}
+
+fn deserialize_some<'de, D>(deserializer: D) -> core::result::Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// Executes the rpc method and returns its json result.
+///
+/// With an idempotency key, the result of the first successful call of a
+/// mutating method is stored and returned for the retries. The key is claimed in the
+/// transaction of the call, so it is released if the call fails (or never
+/// completes), and a concurrent retry waits for its result.
+pub async fn _rpc_handler(
+    rpc_router: &RpcRouter,
+    mm: ModelManager,
+    ctx: Ctx,
+    rpc_req: RpcRequest,
+) -> Result<Value> {
     let RpcRequest {
-        id: rpc_id,
         method,
         params,
+        idempotency_key,
+        ..
     } = rpc_req;
 
-    let result_json = match method.as_str() {
-        "create_task" => exec_rpc_fn!(create_task, ctx, mm, params),
-        "list_tasks" => exec_rpc_fn!(list_tasks, ctx, mm),
-        "update_task" => exec_rpc_fn!(update_task, ctx, mm, params),
-        "delete_task" => exec_rpc_fn!(delete_task, ctx, mm, params),
-        _ => return Err(Error::RpcMethodUnknown(method)),
+    let Some(key) = idempotency_key.filter(|_| is_mutating_method(&method)) else {
+        return rpc_router.call(&method, ctx, mm, params).await;
     };
 
-    let body_response = json!({
-        "id": rpc_id,
-        "result": result_json,
-    });
+    mm.transaction(|mm| async move {
+        match IdempotencyBmc::claim(&ctx, &mm, &key, &method, params.as_ref()).await? {
+            IdempotencyClaim::Replay(result) => Ok(result),
+            IdempotencyClaim::Claimed => {
+                let result = rpc_router
+                    .call(&method, ctx.clone(), mm.clone(), params)
+                    .await?;
+                IdempotencyBmc::complete(&ctx, &mm, &key, &result).await?;
+
+                Ok(result)
+            }
+        }
+    })
+    .await
+}
+
+/// Whether the `method` may change data, the read methods being
+/// `rpc.discover` and the `get_*` and `list_*` ones.
+fn is_mutating_method(method: &str) -> bool {
+    !(method == RPC_DISCOVER_METHOD || method.starts_with("get_") || method.starts_with("list_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::task::TaskBmc;
+    use crate::util::now_utc;
+    use anyhow::Result;
+    use axum::body::HttpBody;
+    use serde_json::json;
+    use serial_test::serial;
+    use uuid::Uuid;
+
+    /// Executes the `payload` with the ctx of `user_id`, returning the json
+    /// response (`null` without response).
+    async fn exec_payload(mm: &ModelManager, user_id: i64, payload: Value) -> Result<Value> {
+        let (_, rpc_response) = exec_rpc_payload(
+            &all_rpc_router(),
+            mm.clone(),
+            _dev_utils::ctx_test(user_id),
+            payload.to_string().as_bytes(),
+            None,
+        )
+        .await;
+
+        Ok(serde_json::to_value(rpc_response)?)
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_exec_rpc_batch_concurrent_order_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        // Calls of different durations, the unknown methods being answered
+        // without a db query.
+        // More calls than database connections.
+        let fx_len = DB_MAX_CONNECTIONS as i64 * 2 + 1;
+        let fx_values: Vec<Value> = (0..fx_len)
+            .map(|id| {
+                let method = if id % 2 == 0 {
+                    "list_tasks"
+                } else {
+                    "unknown_method"
+                };
+                json!({"jsonrpc": "2.0", "id": id, "method": method})
+            })
+            .collect();
+
+        let (_, rpc_responses) = exec_rpc_batch(
+            &all_rpc_router(),
+            mm,
+            _dev_utils::ctx_test(1000),
+            fx_values,
+            true,
+        )
+        .await;
+
+        let responses = serde_json::to_value(rpc_responses)?;
+        let ids: Vec<Value> = responses
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|response| response["id"].clone())
+            .collect();
+        let fx_ids: Vec<Value> = (0..fx_len).map(|id| json!(id)).collect();
+        assert_eq!(ids, fx_ids, "in the order of the calls");
+        assert_eq!(responses[1]["error"]["code"], -32601);
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_exec_rpc_payload_batch_err_size() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let fx_call = json!({"jsonrpc": "2.0", "id": 1, "method": "list_tasks"});
+        let fx_too_large = vec![fx_call; config().RPC_BATCH_MAX_SIZE + 1];
+
+        for fx_payload in [json!([]), json!(fx_too_large)] {
+            let response = exec_payload(&mm, 1000, fx_payload).await?;
+            assert_eq!(response["error"]["code"], -32600, "{response}");
+            assert_eq!(response["id"], Value::Null);
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_exec_rpc_payload_batch_mixed_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let fx_payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "list_tasks"},
+            {"jsonrpc": "1.0", "id": 2, "method": "list_tasks"},
+            42,
+            {"jsonrpc": "2.0", "id": 4, "method": "unknown_method"},
+        ]);
+
+        let response = exec_payload(&mm, 1000, fx_payload).await?;
+
+        let responses = response.as_array().cloned().unwrap_or_default();
+        assert_eq!(responses.len(), 4, "one response per call: {response}");
+        assert!(responses[0]["result"]["items"].is_array());
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["code"], -32600);
+        assert_eq!(responses[2]["id"], Value::Null);
+        assert_eq!(responses[2]["error"]["code"], -32600);
+        assert_eq!(responses[3]["id"], 4);
+        assert_eq!(responses[3]["error"]["code"], -32601);
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_exec_rpc_payload_notification_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let fx_notification = json!({"jsonrpc": "2.0", "method": "list_tasks"});
+
+        // -- Check single and all notifications batch, 204 without body
+        for fx_payload in [
+            fx_notification.clone(),
+            json!([fx_notification, fx_notification]),
+        ] {
+            let rpc_state = RpcState {
+                mm: mm.clone(),
+                rpc_router: Arc::new(all_rpc_router()),
+            };
+            let response = rpc_handler(
+                State(rpc_state),
+                CtxW(_dev_utils::ctx_test(1000), now_utc()),
+                HeaderMap::new(),
+                Bytes::from(fx_payload.to_string()),
+            )
+            .await;
+
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+            assert_eq!(response.body().size_hint().exact(), Some(0));
+        }
+
+        // -- Check batch, the notifications left out
+        let fx_payload = json!([
+            fx_notification,
+            {"jsonrpc": "2.0", "id": 1, "method": "list_tasks"},
+            {"jsonrpc": "2.0", "method": "unknown_method"},
+        ]);
+        let response = exec_payload(&mm, 1000, fx_payload).await?;
+        let responses = response.as_array().cloned().unwrap_or_default();
+        assert_eq!(responses.len(), 1, "{response}");
+        assert_eq!(responses[0]["id"], 1);
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_exec_rpc_payload_idempotency_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        // Unique per run, the keys are only removed when expired.
+        let fx_key = format!("test_exec_rpc_payload_idempotency_ok-{}", Uuid::new_v4());
+
+        // -- Check replay
+        let fx_payload = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "create_task",
+            "params": {"data": {"title": "test_exec_rpc_payload_idempotency_ok"}},
+            "idempotency_key": fx_key,
+        });
+        let task_id = exec_payload(&mm, 1000, fx_payload.clone()).await?["result"]["id"].clone();
+        let replay = exec_payload(&mm, 1000, fx_payload).await?;
+        assert!(task_id.is_i64());
+        assert_eq!(replay["result"]["id"], task_id);
+
+        // -- Check key ignored for reads
+        let fx_payload = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "list_tasks",
+            "idempotency_key": fx_key,
+        });
+        let response = exec_payload(&mm, 1000, fx_payload).await?;
+        assert!(response["result"]["items"].is_array(), "{response}");
+
+        // -- Check failed call released
+        let fx_payload = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "delete_task",
+            "params": {"id": 999_999},
+            "idempotency_key": format!("{fx_key}-err"),
+        });
+        for _ in 0..2 {
+            let response = exec_payload(&mm, 1000, fx_payload.clone()).await?;
+            assert_eq!(response["error"]["code"], -32004, "not in progress");
+        }
+
+        let task_id = task_id.as_i64().unwrap_or_default();
+        TaskBmc::purge(&_dev_utils::ctx_test(1000), &mm, task_id).await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rpc_request_ok() -> Result<()> {
+        let fx_value = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "list_tasks",
+        });
+
+        let rpc_req = parse_rpc_request(fx_value).map_err(|(_, ex)| ex)?;
+
+        assert_eq!(rpc_req.id, Some(json!(1)));
+        assert_eq!(rpc_req.method, "list_tasks");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rpc_request_err_version() -> Result<()> {
+        let fx_value = json!({
+            "jsonrpc": "1.0",
+            "id": "a1",
+            "method": "list_tasks",
+        });
+
+        let res = parse_rpc_request(fx_value);
+
+        assert!(
+            matches!(&res, Err((Some(id), Error::RpcRequestInvalid(_))) if id == "a1"),
+            "Should have matched Err((Some(\"a1\"), Error::RpcRequestInvalid(_)))"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rpc_request_err_not_object() -> Result<()> {
+        let res = parse_rpc_request(json!("list_tasks"));
+
+        assert!(
+            matches!(res, Err((None, Error::RpcRequestInvalid(_)))),
+            "Should have matched Err((None, Error::RpcRequestInvalid(_)))"
+        );
 
-    Ok(Json(body_response))
+        Ok(())
+    }
 }
@@ -0,0 +1,86 @@
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+
+pub const OPENRPC_VERSION: &str = "1.2.6";
+
+/// Rpc method returning the OpenRPC document of the service.
+pub const RPC_DISCOVER_METHOD: &str = "rpc.discover";
+
+/// Schema generator referencing shared schemas from the document
+/// `components`.
+pub fn new_schema_generator() -> SchemaGenerator {
+    SchemaSettings::draft07()
+        .with(|settings| {
+            settings.definitions_path = "#/components/schemas/".to_string();
+            settings.meta_schema = None;
+        })
+        .into_generator()
+}
+
+/// OpenRPC document of `methods` (OpenRPC method objects), with the
+/// schemas collected by `gen` as components.
+pub fn openrpc_document(methods: Vec<Value>, mut gen: SchemaGenerator) -> Value {
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": methods,
+        "components": {
+            "schemas": gen.take_definitions(),
+        },
+    })
+}
+
+/// OpenRPC method object for a method without params.
+pub fn method_object<R: JsonSchema>(name: &str, gen: &mut SchemaGenerator) -> Value {
+    json!({
+        "name": name,
+        "paramStructure": "by-name",
+        "params": [],
+        "result": result_descriptor::<R>(gen),
+    })
+}
+
+/// OpenRPC method object for a method taking `P` as params.
+///
+/// Each property of the `P` object becomes a by-name param.
+pub fn method_object_with_params<P: JsonSchema, R: JsonSchema>(
+    name: &str,
+    gen: &mut SchemaGenerator,
+) -> Value {
+    let params_schema = gen.root_schema_for::<P>().schema;
+
+    let params: Vec<Value> = params_schema
+        .object
+        .map(|object| {
+            object
+                .properties
+                .into_iter()
+                .map(|(param_name, schema)| {
+                    json!({
+                        "name": param_name,
+                        "required": object.required.contains(&param_name),
+                        "schema": schema,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    json!({
+        "name": name,
+        "paramStructure": "by-name",
+        "params": params,
+        "result": result_descriptor::<R>(gen),
+    })
+}
+
+fn result_descriptor<R: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    json!({
+        "name": "result",
+        "schema": gen.subschema_for::<R>(),
+    })
+}
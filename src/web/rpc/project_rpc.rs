@@ -0,0 +1,84 @@
+use crate::ctx::Ctx;
+use crate::model::list_options::Page;
+use crate::model::project::{Project, ProjectBmc, ProjectForCreate, ProjectForUpdate};
+use crate::model::ModelManager;
+use crate::web::rpc::{
+    rpc_router, ParamsForCreate, ParamsForUpdateUnversioned, ParamsIded, ParamsList, RpcRouter,
+};
+use crate::web::Result;
+
+pub fn rpc_router() -> RpcRouter {
+    rpc_router!(
+        create_project,
+        list_projects,
+        update_project,
+        delete_project
+    )
+}
+
+pub async fn create_project(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<ProjectForCreate>,
+) -> Result<Project> {
+    let ParamsForCreate { data } = params;
+
+    mm.transaction(|mm| async move {
+        let id = ProjectBmc::create(&ctx, &mm, data).await?;
+        let project = ProjectBmc::get(&ctx, &mm, id).await?;
+
+        Ok(project)
+    })
+    .await
+}
+
+pub async fn list_projects(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList,
+) -> Result<Page<Project>> {
+    let ParamsList {
+        filters,
+        list_options,
+    } = params;
+
+    let projects = ProjectBmc::list(
+        &ctx,
+        &mm,
+        filters.unwrap_or_default(),
+        list_options.unwrap_or_default(),
+    )
+    .await?;
+
+    Ok(projects)
+}
+
+pub async fn update_project(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdateUnversioned<ProjectForUpdate>,
+) -> Result<Project> {
+    let ParamsForUpdateUnversioned { id, data } = params;
+
+    mm.transaction(|mm| async move {
+        ProjectBmc::update(&ctx, &mm, id, data).await?;
+        let project = ProjectBmc::get(&ctx, &mm, id).await?;
+
+        Ok(project)
+    })
+    .await
+}
+
+/// Deletes the project, refused with `ENTITY_IN_USE` while it has tasks.
+/// Returned as it was before.
+pub async fn delete_project(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Project> {
+    let ParamsIded { id } = params;
+
+    mm.transaction(|mm| async move {
+        let project = ProjectBmc::get(&ctx, &mm, id).await?;
+        ProjectBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(project)
+    })
+    .await
+}
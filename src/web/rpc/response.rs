@@ -0,0 +1,103 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+use serde_with::skip_serializing_none;
+
+use crate::web::{self, ClientError};
+
+use super::JSONRPC_VERSION;
+
+/// Response to a JSON-RPC payload, a single response object or a batch of
+/// them.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum RpcPayloadResponse {
+    Single(RpcResponse),
+    Batch(Vec<RpcResponse>),
+}
+
+/// JSON-RPC 2.0 response object.
+///
+/// Exactly one of `result` or `error` is serialized.
+#[skip_serializing_none]
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn result(id: Option<Value>, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION,
+            id: id.unwrap_or(Value::Null),
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Option<Value>, web_error: &web::Error) -> Self {
+        let (_, client_error) = web_error.client_status_and_error();
+
+        Self {
+            jsonrpc: JSONRPC_VERSION,
+            id: id.unwrap_or(Value::Null),
+            result: None,
+            error: Some(client_error.into()),
+        }
+    }
+}
+
+/// JSON-RPC 2.0 error object.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl From<ClientError> for RpcError {
+    fn from(client_error: ClientError) -> Self {
+        let code = client_error.rpc_code();
+        let message = client_error.as_ref().to_string();
+
+        // ClientError serializes as `{"message": .., "detail": ..}`.
+        let data = json!(client_error)
+            .get_mut("detail")
+            .map(|detail| detail.take());
+
+        Self {
+            code,
+            message,
+            data,
+        }
+    }
+}
+
+/// Result of one item of a bulk method, its `result` or its `error`.
+///
+/// Bulk methods return one per params item, in order.
+#[skip_serializing_none]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BulkItemResult<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+impl<T> From<web::Result<T>> for BulkItemResult<T> {
+    fn from(res: web::Result<T>) -> Self {
+        match res {
+            Ok(result) => Self {
+                result: Some(result),
+                error: None,
+            },
+            Err(web_error) => Self {
+                result: None,
+                error: Some(web_error.client_status_and_error().1.into()),
+            },
+        }
+    }
+}
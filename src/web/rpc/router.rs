@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use schemars::gen::SchemaGenerator;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{from_value, json, to_value, Value};
+
+use crate::ctx::Ctx;
+use crate::model::ModelManager;
+use crate::web::{Error, Result};
+
+use super::openrpc::{self, RPC_DISCOVER_METHOD};
+
+/// Builds an `RpcRouter` from rpc handler functions, registered under their
+/// function names.
+///
+/// ```ignore
+/// rpc_router!(create_task, list_tasks)
+/// ```
+macro_rules! rpc_router {
+    ($($fn_name:ident),+ $(,)?) => {
+        {
+            let mut router = $crate::web::rpc::RpcRouter::new();
+            $(
+                router = router.add(stringify!($fn_name), $fn_name);
+            )+
+            router
+        }
+    };
+}
+pub(crate) use rpc_router;
+
+/// Registry of the rpc methods, by method name.
+///
+/// Each rpc module exposes its own router (e.g., `task_rpc::rpc_router()`),
+/// and they are merged with `extend`.
+#[derive(Default)]
+pub struct RpcRouter {
+    route_by_name: HashMap<&'static str, Box<dyn RpcHandlerWrapperTrait>>,
+}
+
+impl RpcRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` under `name`.
+    ///
+    /// Panics if a method with the same name is already registered.
+    pub fn add<H, T, R>(self, name: &'static str, handler: H) -> Self
+    where
+        H: RpcHandler<T, R>,
+        T: 'static,
+        R: 'static,
+    {
+        self.add_dyn(name, Box::new(RpcHandlerWrapper::new(handler)))
+    }
+
+    /// Merges the methods of `other` into this router.
+    ///
+    /// Panics if both routers register the same method name.
+    pub fn extend(mut self, other: RpcRouter) -> Self {
+        for (name, route) in other.route_by_name {
+            self = self.add_dyn(name, route);
+        }
+        self
+    }
+
+    /// Calls the `method` handler, or answers `rpc.discover` with the
+    /// OpenRPC document of this router.
+    pub async fn call(
+        &self,
+        method: &str,
+        ctx: Ctx,
+        mm: ModelManager,
+        params: Option<Value>,
+    ) -> Result<Value> {
+        if method == RPC_DISCOVER_METHOD {
+            return Ok(self.openrpc_document());
+        }
+
+        match self.route_by_name.get_key_value(method) {
+            Some((name, route)) => route.call(name, ctx, mm, params).await,
+            None => Err(Error::RpcMethodUnknown(method.to_string())),
+        }
+    }
+
+    /// OpenRPC document of all the registered methods, sorted by name.
+    pub fn openrpc_document(&self) -> Value {
+        let mut gen = openrpc::new_schema_generator();
+
+        let mut names: Vec<&&'static str> = self.route_by_name.keys().collect();
+        names.sort();
+
+        let methods = names
+            .into_iter()
+            .map(|name| self.route_by_name[name].openrpc_method(name, &mut gen))
+            .collect();
+
+        openrpc::openrpc_document(methods, gen)
+    }
+
+    fn add_dyn(mut self, name: &'static str, route: Box<dyn RpcHandlerWrapperTrait>) -> Self {
+        if self.route_by_name.insert(name, route).is_some() {
+            panic!("RpcRouter - method '{name}' registered more than once");
+        }
+        self
+    }
+}
+
+pub type PinFutureValue = Pin<Box<dyn Future<Output = Result<Value>> + Send>>;
+
+/// Implemented for the async functions usable as rpc methods:
+/// - `async fn(Ctx, ModelManager) -> web::Result<R>`
+/// - `async fn(Ctx, ModelManager, P) -> web::Result<R>`
+///
+/// where `P: DeserializeOwned` is built from the request `params`, and
+/// `R: Serialize` becomes the response `result`.
+/// Both also implement `JsonSchema` for the OpenRPC document.
+pub trait RpcHandler<T, R>: Clone + Send + Sync + Sized + 'static {
+    fn call(
+        self,
+        method: &'static str,
+        ctx: Ctx,
+        mm: ModelManager,
+        params: Option<Value>,
+    ) -> PinFutureValue;
+
+    /// OpenRPC method object of this handler, registered as `name`.
+    fn openrpc_method(name: &str, gen: &mut SchemaGenerator) -> Value;
+}
+
+impl<F, Fut, R> RpcHandler<(), R> for F
+where
+    F: FnOnce(Ctx, ModelManager) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<R>> + Send + 'static,
+    R: Serialize + JsonSchema,
+{
+    fn call(
+        self,
+        _method: &'static str,
+        ctx: Ctx,
+        mm: ModelManager,
+        _params: Option<Value>,
+    ) -> PinFutureValue {
+        Box::pin(async move {
+            let result = self(ctx, mm).await?;
+
+            Ok(to_value(result)?)
+        })
+    }
+
+    fn openrpc_method(name: &str, gen: &mut SchemaGenerator) -> Value {
+        openrpc::method_object::<R>(name, gen)
+    }
+}
+
+impl<F, Fut, P, R> RpcHandler<(P,), R> for F
+where
+    F: FnOnce(Ctx, ModelManager, P) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<R>> + Send + 'static,
+    P: DeserializeOwned + JsonSchema + Send,
+    R: Serialize + JsonSchema,
+{
+    fn call(
+        self,
+        method: &'static str,
+        ctx: Ctx,
+        mm: ModelManager,
+        params: Option<Value>,
+    ) -> PinFutureValue {
+        Box::pin(async move {
+            // Missing params are read as the empty params object, so they
+            // are only required when `P` has required properties.
+            let is_missing = params.is_none();
+            let params: P = from_value(params.unwrap_or_else(|| json!({}))).map_err(|ex| {
+                if is_missing {
+                    Error::RpcMissingParams {
+                        method: method.to_string(),
+                    }
+                } else {
+                    Error::RpcFailJsonParams {
+                        method: method.to_string(),
+                        cause: ex.to_string(),
+                    }
+                }
+            })?;
+
+            let result = self(ctx, mm, params).await?;
+
+            Ok(to_value(result)?)
+        })
+    }
+
+    fn openrpc_method(name: &str, gen: &mut SchemaGenerator) -> Value {
+        openrpc::method_object_with_params::<P, R>(name, gen)
+    }
+}
+
+/// Type erased `RpcHandler`, so handlers of different signatures can live
+/// in the same router.
+trait RpcHandlerWrapperTrait: Send + Sync {
+    fn call(
+        &self,
+        method: &'static str,
+        ctx: Ctx,
+        mm: ModelManager,
+        params: Option<Value>,
+    ) -> PinFutureValue;
+
+    fn openrpc_method(&self, name: &str, gen: &mut SchemaGenerator) -> Value;
+}
+
+struct RpcHandlerWrapper<H, T, R> {
+    handler: H,
+    _marker: PhantomData<fn() -> (T, R)>,
+}
+
+impl<H, T, R> RpcHandlerWrapper<H, T, R> {
+    fn new(handler: H) -> Self {
+        Self {
+            handler,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<H, T, R> RpcHandlerWrapperTrait for RpcHandlerWrapper<H, T, R>
+where
+    H: RpcHandler<T, R>,
+    T: 'static,
+    R: 'static,
+{
+    fn call(
+        &self,
+        method: &'static str,
+        ctx: Ctx,
+        mm: ModelManager,
+        params: Option<Value>,
+    ) -> PinFutureValue {
+        self.handler.clone().call(method, ctx, mm, params)
+    }
+
+    fn openrpc_method(&self, name: &str, gen: &mut SchemaGenerator) -> Value {
+        H::openrpc_method(name, gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_dev_utils;
+
+    use super::*;
+    use anyhow::Result;
+    use serde::Deserialize;
+    use serde_json::json;
+    use serial_test::serial;
+
+    #[derive(Deserialize, JsonSchema)]
+    struct ParamsEcho {
+        value: i64,
+    }
+
+    async fn echo(_ctx: Ctx, _mm: ModelManager, params: ParamsEcho) -> crate::web::Result<i64> {
+        Ok(params.value)
+    }
+
+    async fn ping(_ctx: Ctx, _mm: ModelManager) -> crate::web::Result<&'static str> {
+        Ok("pong")
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_call_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let router = rpc_router!(ping).extend(rpc_router!(echo));
+
+        let res = router
+            .call("echo", ctx.clone(), mm.clone(), Some(json!({"value": 3})))
+            .await?;
+        assert_eq!(res, json!(3));
+
+        let res = router.call("ping", ctx, mm, None).await?;
+        assert_eq!(res, json!("pong"));
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_call_err_params() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let router = rpc_router!(echo);
+
+        let res = router.call("echo", ctx.clone(), mm.clone(), None).await;
+        assert!(
+            matches!(&res, Err(Error::RpcMissingParams { method }) if method == "echo"),
+            "Should have matched Err(Error::RpcMissingParams) but was `{res:?}`"
+        );
+
+        let res = router
+            .call("echo", ctx.clone(), mm.clone(), Some(json!({"value": "3"})))
+            .await;
+        assert!(
+            matches!(&res, Err(Error::RpcFailJsonParams { method, .. }) if method == "echo"),
+            "Should have matched Err(Error::RpcFailJsonParams) but was `{res:?}`"
+        );
+
+        let res = router.call("unknown", ctx, mm, None).await;
+        assert!(
+            matches!(&res, Err(Error::RpcMethodUnknown(method)) if method == "unknown"),
+            "Should have matched Err(Error::RpcMethodUnknown) but was `{res:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_call_discover_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let router = rpc_router!(ping, echo);
+
+        let doc = router.call(RPC_DISCOVER_METHOD, ctx, mm, None).await?;
+
+        assert_eq!(doc["methods"][0]["name"], "echo");
+        assert_eq!(doc["methods"][0]["params"][0]["name"], "value");
+        assert_eq!(doc["methods"][0]["params"][0]["required"], true);
+        assert_eq!(doc["methods"][1]["name"], "ping");
+        assert_eq!(doc["methods"][1]["result"]["schema"]["type"], "string");
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "registered more than once")]
+    fn test_extend_duplicate_panics() {
+        let _ = rpc_router!(ping).extend(rpc_router!(ping));
+    }
+}
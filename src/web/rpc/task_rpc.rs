@@ -1,48 +1,339 @@
+use std::collections::HashMap;
+
 use crate::ctx::Ctx;
-use crate::model::task::{Task, TaskBmc, TaskForCreate, TaskForUpdate};
-use crate::model::ModelManager;
-use crate::web::rpc::ParamsForCreate;
-use crate::web::Result;
+use crate::model::label::LabelBmc;
+use crate::model::list_options::Page;
+use crate::model::task::{
+    Task, TaskBmc, TaskForCreate, TaskForUpdate, TaskProgress, TaskWithLabels,
+};
+use crate::model::{self, ModelManager};
+use crate::web::rpc::{rpc_router, BulkItemResult, ParamsForCreate, RpcRouter};
+use crate::web::{Error, Result};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::{
+    ParamsForCreateMany, ParamsForUpdate, ParamsForUpdateMany, ParamsIded, ParamsIdeds, ParamsList,
+};
+
+pub fn rpc_router() -> RpcRouter {
+    rpc_router!(
+        create_task,
+        list_tasks,
+        update_task,
+        delete_task,
+        restore_task,
+        purge_task,
+        create_tasks,
+        update_tasks,
+        delete_tasks,
+        add_task_label,
+        remove_task_label,
+        get_task_subtree,
+        move_task,
+        get_task_progress
+    )
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsTaskLabel {
+    task_id: i64,
+    label_id: i64,
+}
 
-use super::{ParamsForUpdate, ParamsIded};
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsMoveTask {
+    id: i64,
+    /// New parent, `null` to make it a root task.
+    parent_id: Option<i64>,
+}
 
 pub async fn create_task(
     ctx: Ctx,
     mm: ModelManager,
     params: ParamsForCreate<TaskForCreate>,
-) -> Result<Task> {
+) -> Result<TaskWithLabels> {
     let ParamsForCreate { data } = params;
 
-    let id = TaskBmc::create(&ctx, &mm, data).await?;
-    let task = TaskBmc::get(&ctx, &mm, id).await?;
+    mm.transaction(|mm| async move {
+        let id = TaskBmc::create(&ctx, &mm, data).await?;
+        let task = TaskBmc::get(&ctx, &mm, id).await?;
 
-    Ok(task)
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
 }
 
-pub async fn list_tasks(ctx: Ctx, mm: ModelManager) -> Result<Vec<Task>> {
-    let tasks = TaskBmc::list(&ctx, &mm).await?;
+/// Lists the tasks, of a project with a `project_id` filter (`null` op for
+/// the tasks without project), or with a label with a `label_id` filter
+/// (one filter per label for the tasks with all of them).
+pub async fn list_tasks(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList,
+) -> Result<Page<TaskWithLabels>> {
+    let ParamsList {
+        filters,
+        list_options,
+    } = params;
+
+    let Page {
+        items,
+        total,
+        limit,
+        offset,
+        next_cursor,
+    } = TaskBmc::list(
+        &ctx,
+        &mm,
+        filters.unwrap_or_default(),
+        list_options.unwrap_or_default(),
+    )
+    .await?;
 
-    Ok(tasks)
+    Ok(Page {
+        items: TaskBmc::with_labels(&ctx, &mm, items).await?,
+        total,
+        limit,
+        offset,
+        next_cursor,
+    })
 }
 
 pub async fn update_task(
     ctx: Ctx,
     mm: ModelManager,
     params: ParamsForUpdate<TaskForUpdate>,
-) -> Result<Task> {
-    let ParamsForUpdate { id, data } = params;
+) -> Result<TaskWithLabels> {
+    let ParamsForUpdate { id, data, version } = params;
 
-    TaskBmc::update(&ctx, &mm, id, data).await?;
-    let task = TaskBmc::get(&ctx, &mm, id).await?;
+    mm.transaction(|mm| async move {
+        TaskBmc::update(&ctx, &mm, id, data, version).await?;
+        let task = TaskBmc::get(&ctx, &mm, id).await?;
 
-    Ok(task)
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
 }
 
-pub async fn delete_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<Task> {
+/// Soft deletes the task, returned with its `deleted_at`.
+pub async fn delete_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<TaskWithLabels> {
     let ParamsIded { id } = params;
 
-    TaskBmc::delete(&ctx, &mm, id).await?;
-    let task = TaskBmc::get(&ctx, &mm, id).await?;
+    mm.transaction(|mm| async move {
+        TaskBmc::delete(&ctx, &mm, id).await?;
+        let task = TaskBmc::get_include_deleted(&ctx, &mm, id).await?;
+
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
+}
+
+pub async fn restore_task(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<TaskWithLabels> {
+    let ParamsIded { id } = params;
+
+    mm.transaction(|mm| async move {
+        TaskBmc::restore(&ctx, &mm, id).await?;
+        let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
+}
+
+/// Deletes the task for good, returned as it was before.
+pub async fn purge_task(ctx: Ctx, mm: ModelManager, params: ParamsIded) -> Result<TaskWithLabels> {
+    let ParamsIded { id } = params;
+
+    mm.transaction(|mm| async move {
+        let task = TaskBmc::get_include_deleted(&ctx, &mm, id).await?;
+        // Before the purge, which detaches the labels.
+        let task = with_labels(&ctx, &mm, task).await?;
+        TaskBmc::purge(&ctx, &mm, id).await?;
+
+        Ok(task)
+    })
+    .await
+}
+
+/// Creates the valid tasks, the invalid ones get their `VALIDATION_FAILED`
+/// error.
+pub async fn create_tasks(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreateMany<TaskForCreate>,
+) -> Result<Vec<BulkItemResult<TaskWithLabels>>> {
+    let ParamsForCreateMany { data } = params;
+
+    mm.transaction(|mm| async move {
+        let results = TaskBmc::create_many(&ctx, &mm, data).await?;
+
+        bulk_item_results(&ctx, &mm, results, false).await
+    })
+    .await
+}
+
+/// Updates the tasks of `ids` (one result per id), or the tasks matching
+/// the `filters` (one result per updated task).
+pub async fn update_tasks(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdateMany<TaskForUpdate>,
+) -> Result<Vec<BulkItemResult<TaskWithLabels>>> {
+    let ParamsForUpdateMany { ids, filters, data } = params;
+
+    mm.transaction(|mm| async move {
+        let results = match (ids, filters) {
+            (Some(ids), None) => TaskBmc::update_many(&ctx, &mm, &ids, data).await?,
+            (None, Some(filters)) => {
+                TaskBmc::update_many_by_filters(&ctx, &mm, filters, data).await?
+            }
+            _ => {
+                return Err(Error::RpcFailJsonParams {
+                    method: "update_tasks".to_string(),
+                    cause: "exactly one of ids or filters must be set".to_string(),
+                })
+            }
+        };
+
+        bulk_item_results(&ctx, &mm, results, false).await
+    })
+    .await
+}
+
+/// Soft deletes the tasks of `ids`, returned with their `deleted_at`.
+pub async fn delete_tasks(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIdeds,
+) -> Result<Vec<BulkItemResult<TaskWithLabels>>> {
+    let ParamsIdeds { ids } = params;
+
+    mm.transaction(|mm| async move {
+        let results = TaskBmc::delete_many(&ctx, &mm, &ids).await?;
+
+        bulk_item_results(&ctx, &mm, results, true).await
+    })
+    .await
+}
+
+/// Attaches the label to the task, returned with its labels.
+pub async fn add_task_label(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsTaskLabel,
+) -> Result<TaskWithLabels> {
+    let ParamsTaskLabel { task_id, label_id } = params;
+
+    mm.transaction(|mm| async move {
+        LabelBmc::attach(&ctx, &mm, task_id, label_id).await?;
+        let task = TaskBmc::get(&ctx, &mm, task_id).await?;
+
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
+}
+
+/// Detaches the label from the task, returned with its labels.
+pub async fn remove_task_label(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsTaskLabel,
+) -> Result<TaskWithLabels> {
+    let ParamsTaskLabel { task_id, label_id } = params;
+
+    mm.transaction(|mm| async move {
+        LabelBmc::detach(&ctx, &mm, task_id, label_id).await?;
+        let task = TaskBmc::get(&ctx, &mm, task_id).await?;
+
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
+}
+
+/// Task and its subtasks at any depth, as a flat list with the parents
+/// first (by depth, then id). Clients rebuild the tree from `parent_id`.
+pub async fn get_task_subtree(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<Vec<TaskWithLabels>> {
+    let ParamsIded { id } = params;
+
+    let tasks = TaskBmc::get_subtree(&ctx, &mm, id).await?;
+
+    Ok(TaskBmc::with_labels(&ctx, &mm, tasks).await?)
+}
+
+/// Moves the task under another parent (with its subtasks), returned with
+/// its labels. A parent in its own subtree fails on `parent_id`.
+pub async fn move_task(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsMoveTask,
+) -> Result<TaskWithLabels> {
+    let ParamsMoveTask { id, parent_id } = params;
+
+    mm.transaction(|mm| async move {
+        TaskBmc::move_to_parent(&ctx, &mm, id, parent_id).await?;
+        let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+        with_labels(&ctx, &mm, task).await
+    })
+    .await
+}
+
+pub async fn get_task_progress(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<TaskProgress> {
+    let ParamsIded { id } = params;
+
+    Ok(TaskBmc::progress(&ctx, &mm, id).await?)
+}
+
+async fn with_labels(ctx: &Ctx, mm: &ModelManager, task: Task) -> Result<TaskWithLabels> {
+    let mut tasks = TaskBmc::with_labels(ctx, mm, vec![task]).await?;
+
+    Ok(tasks.remove(0))
+}
+
+/// Bulk method results of the model per item `results`, with the task (and
+/// its labels) of each id.
+async fn bulk_item_results(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    results: Vec<model::Result<i64>>,
+    include_deleted: bool,
+) -> Result<Vec<BulkItemResult<TaskWithLabels>>> {
+    let ids: Vec<i64> = results
+        .iter()
+        .filter_map(|res| res.as_ref().ok())
+        .copied()
+        .collect();
+    let tasks = TaskBmc::get_many(ctx, mm, &ids, include_deleted).await?;
+    let tasks: HashMap<i64, TaskWithLabels> = TaskBmc::with_labels(ctx, mm, tasks)
+        .await?
+        .into_iter()
+        .map(|task| (task.task.id, task))
+        .collect();
 
-    Ok(task)
+    Ok(results
+        .into_iter()
+        .map(|res| {
+            res.and_then(|id| {
+                tasks.get(&id).cloned().ok_or(model::Error::EntityNotFound {
+                    entity: "tasks",
+                    id,
+                })
+            })
+            .map_err(Error::from)
+            .into()
+        })
+        .collect())
 }
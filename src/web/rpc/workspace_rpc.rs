@@ -0,0 +1,104 @@
+use crate::ctx::Ctx;
+use crate::model::workspace::{UserWorkspace, WorkspaceBmc, WorkspaceForCreate, WorkspaceMember};
+use crate::model::ModelManager;
+use crate::web::rpc::{rpc_router, ParamsForCreate, ParamsIded, RpcRouter};
+use crate::web::Result;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+pub fn rpc_router() -> RpcRouter {
+    rpc_router!(
+        create_workspace,
+        list_workspaces,
+        list_workspace_members,
+        add_workspace_member,
+        remove_workspace_member
+    )
+}
+
+/// Params of the member methods, `id` being the workspace.
+#[derive(Deserialize, JsonSchema)]
+pub struct ParamsWorkspaceMember {
+    id: i64,
+    user_id: i64,
+}
+
+/// Creates a workspace owned by the ctx user, which becomes its default
+/// workspace if the request has none (see `routes_workspace` to switch).
+pub async fn create_workspace(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<WorkspaceForCreate>,
+) -> Result<UserWorkspace> {
+    let ParamsForCreate { data } = params;
+
+    mm.transaction(|mm| async move {
+        let id = WorkspaceBmc::create(&ctx, &mm, data).await?;
+
+        let ctx = match ctx.workspace_id() {
+            Some(_) => ctx,
+            None => {
+                WorkspaceBmc::set_default(&ctx, &mm, id).await?;
+                ctx.with_workspace_id(Some(id))
+            }
+        };
+        let workspace = WorkspaceBmc::get(&ctx, &mm, id).await?;
+
+        Ok(workspace)
+    })
+    .await
+}
+
+pub async fn list_workspaces(ctx: Ctx, mm: ModelManager) -> Result<Vec<UserWorkspace>> {
+    let workspaces = WorkspaceBmc::list(&ctx, &mm).await?;
+
+    Ok(workspaces)
+}
+
+/// Members of the workspace, for a member of it.
+pub async fn list_workspace_members(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<Vec<WorkspaceMember>> {
+    let ParamsIded { id } = params;
+
+    let members = WorkspaceBmc::list_members(&ctx, &mm, id).await?;
+
+    Ok(members)
+}
+
+/// Adds the user as a member of the workspace, for an owner of it.
+pub async fn add_workspace_member(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsWorkspaceMember,
+) -> Result<Vec<WorkspaceMember>> {
+    let ParamsWorkspaceMember { id, user_id } = params;
+
+    mm.transaction(|mm| async move {
+        WorkspaceBmc::add_member(&ctx, &mm, id, user_id).await?;
+        let members = WorkspaceBmc::list_members(&ctx, &mm, id).await?;
+
+        Ok(members)
+    })
+    .await
+}
+
+/// Removes the member from the workspace, for an owner of it, the owners
+/// cannot be removed.
+pub async fn remove_workspace_member(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsWorkspaceMember,
+) -> Result<Vec<WorkspaceMember>> {
+    let ParamsWorkspaceMember { id, user_id } = params;
+
+    mm.transaction(|mm| async move {
+        WorkspaceBmc::remove_member(&ctx, &mm, id, user_id).await?;
+        let members = WorkspaceBmc::list_members(&ctx, &mm, id).await?;
+
+        Ok(members)
+    })
+    .await
+}
@@ -0,0 +1,313 @@
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{OriginalUri, State};
+use axum::http::Uri;
+use axum::response::Response;
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info};
+
+use crate::ctx::Ctx;
+use crate::log;
+use crate::model::event::ModelEvent;
+use crate::model::workspace::WorkspaceBmc;
+use crate::model::ModelManager;
+use crate::util::duration_until;
+use crate::web::mw_auth::CtxW;
+
+use super::{exec_rpc_payload, RpcState, JSONRPC_VERSION};
+
+/// JSON-RPC method of the notifications pushed for model events.
+pub const MODEL_EVENT_METHOD: &str = "model_event";
+
+/// Server pushed JSON-RPC notification.
+#[derive(Serialize)]
+struct RpcNotification<P: Serialize> {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: P,
+}
+
+/// WebSocket entry point of the rpc methods.
+///
+/// Each text (or binary) message is a JSON-RPC payload, answered like on
+/// `/rpc`. The model events are pushed as `model_event` notifications.
+///
+/// The ctx is resolved when connecting, so the socket is closed when its
+/// session expires, or when a payload comes after the user left the ctx
+/// workspace. The client reconnects to get a new ctx.
+pub async fn ws_handler(
+    State(rpc_state): State<RpcState>,
+    ctx_w: CtxW,
+    OriginalUri(uri): OriginalUri,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let CtxW(ctx, session_exp) = ctx_w;
+
+    ws.on_upgrade(move |socket| handle_socket(socket, rpc_state, ctx, session_exp, uri))
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    rpc_state: RpcState,
+    ctx: Ctx,
+    session_exp: OffsetDateTime,
+    uri: Uri,
+) {
+    info!("->> {:<12} - ws connected - {ctx:?}", "WS");
+
+    let RpcState { mm, rpc_router } = rpc_state;
+    let mut events = mm.subscribe_events();
+    let session_end = tokio::time::sleep(duration_until(session_exp));
+    tokio::pin!(session_end);
+
+    loop {
+        tokio::select! {
+            _ = &mut session_end => {
+                close(&mut socket, "session expired").await;
+                break;
+            }
+
+            msg = socket.recv() => {
+                let payload = match msg {
+                    Some(Ok(Message::Text(text))) => text.into_bytes(),
+                    Some(Ok(Message::Binary(bytes))) => bytes,
+                    // Ping/Pong are answered by axum.
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                };
+
+                if !is_still_member(&ctx, &mm).await {
+                    close(&mut socket, "workspace membership lost").await;
+                    break;
+                }
+
+                let (rpc_infos, rpc_response) =
+                    exec_rpc_payload(&rpc_router, mm.clone(), ctx.clone(), &payload, None).await;
+
+                let uuid = uuid::Uuid::new_v4();
+                let _ = log::log_rpc_calls(
+                    uuid.to_string(),
+                    "WS".to_string(),
+                    uri.clone(),
+                    Some(ctx.clone()),
+                    &rpc_infos,
+                )
+                .await;
+
+                if let Some(rpc_response) = rpc_response {
+                    if send_json(&mut socket, &rpc_response).await.is_err() {
+                        break;
+                    }
+                }
+            }
+
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(count)) => {
+                        debug!("{:<12} - ws lagged, {count} events skipped", "WS");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !event.is_visible_to(&ctx) {
+                    continue;
+                }
+
+                if send_json(&mut socket, &model_event_notification(event)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    info!("->> {:<12} - ws disconnected - {ctx:?}", "WS");
+}
+
+/// Whether the ctx user is still a member of the ctx workspace (if any),
+/// false when it cannot be checked.
+async fn is_still_member(ctx: &Ctx, mm: &ModelManager) -> bool {
+    let Some(workspace_id) = ctx.workspace_id() else {
+        return true;
+    };
+
+    matches!(WorkspaceBmc::role(ctx, mm, workspace_id).await, Ok(Some(_)))
+}
+
+/// Closes the socket with a policy violation, the ctx being no more valid.
+async fn close(socket: &mut WebSocket, reason: &'static str) {
+    debug!("{:<12} - ws closed - {reason}", "WS");
+
+    let frame = CloseFrame {
+        code: close_code::POLICY,
+        reason: reason.into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+fn model_event_notification(event: ModelEvent) -> RpcNotification<ModelEvent> {
+    RpcNotification {
+        jsonrpc: JSONRPC_VERSION,
+        method: MODEL_EVENT_METHOD,
+        params: event,
+    }
+}
+
+async fn send_json<T: Serialize>(socket: &mut WebSocket, value: &T) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(value).map_err(axum::Error::new)?;
+
+    socket.send(Message::Text(text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::project::{ProjectBmc, ProjectForCreate};
+    use crate::model::task::{TaskBmc, TaskForCreate};
+    use crate::util::now_utc;
+    use crate::web::rpc::all_rpc_router;
+    use anyhow::Result;
+    use axum::routing::get;
+    use axum::Router;
+    use futures::{SinkExt, Stream, StreamExt};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite;
+
+    /// Serves `handle_socket` with the `ctx` until `session_exp`, returning
+    /// its address.
+    fn serve_ws(mm: ModelManager, ctx: Ctx, session_exp: OffsetDateTime) -> SocketAddr {
+        let rpc_state = RpcState {
+            mm,
+            rpc_router: Arc::new(all_rpc_router()),
+        };
+        let handler = move |State(rpc_state): State<RpcState>, ws: WebSocketUpgrade| async move {
+            ws.on_upgrade(move |socket| {
+                handle_socket(socket, rpc_state, ctx, session_exp, Uri::default())
+            })
+        };
+        let routes = Router::new()
+            .route("/ws", get(handler))
+            .with_state(rpc_state);
+
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(routes.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        addr
+    }
+
+    /// Next message of the `socket`, as json.
+    async fn recv_json<S>(socket: &mut S) -> Result<Value>
+    where
+        S: Stream<Item = core::result::Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        let msg = tokio::time::timeout(Duration::from_secs(5), socket.next()).await?;
+        let text = msg.ok_or(anyhow::anyhow!("ws closed"))??.into_text()?;
+
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Reason of the close frame, the next message of the `socket`.
+    async fn recv_close_reason<S>(socket: &mut S) -> Result<String>
+    where
+        S: Stream<Item = core::result::Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        let msg = tokio::time::timeout(Duration::from_secs(5), socket.next()).await?;
+
+        match msg.ok_or(anyhow::anyhow!("ws closed"))?? {
+            tungstenite::Message::Close(Some(frame)) => Ok(frame.reason.into_owned()),
+            msg => Err(anyhow::anyhow!("not a close frame: {msg:?}")),
+        }
+    }
+
+    /// Session of the tests, not expiring while they run.
+    fn fx_session_exp() -> OffsetDateTime {
+        now_utc() + time::Duration::hours(1)
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_ws_model_event_visible_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        // Member of the workspace, not owner of the tasks of 1000.
+        WorkspaceBmc::add_member(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID, 1001).await?;
+        let addr = serve_ws(mm.clone(), _dev_utils::ctx_test(1001), fx_session_exp());
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws")).await?;
+
+        // An answered call, so the socket is subscribed.
+        let fx_call = json!({"jsonrpc": "2.0", "id": 1, "method": "unknown_method"});
+        socket
+            .send(tungstenite::Message::Text(fx_call.to_string()))
+            .await?;
+        assert_eq!(recv_json(&mut socket).await?["id"], 1);
+
+        let task_c = TaskForCreate {
+            title: "test_ws_model_event_visible_ok".to_string(),
+            ..Default::default()
+        };
+        let task_id = TaskBmc::create(&ctx, &mm, task_c).await?;
+        let project_c = ProjectForCreate {
+            name: "test_ws_model_event_visible_ok".to_string(),
+        };
+        let project_id = ProjectBmc::create(&ctx, &mm, project_c).await?;
+
+        // The task event, of another owner, is filtered out.
+        let notification = recv_json(&mut socket).await?;
+        assert_eq!(notification["method"], MODEL_EVENT_METHOD);
+        assert_eq!(notification["params"]["entity"], "projects");
+        assert_eq!(notification["params"]["entity_id"], project_id);
+        assert_eq!(notification["params"]["kind"], "created");
+
+        TaskBmc::purge(&ctx, &mm, task_id).await?;
+        ProjectBmc::delete(&ctx, &mm, project_id).await?;
+        WorkspaceBmc::remove_member(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID, 1001).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_ws_close_stale_ctx_ok() -> Result<()> {
+        let mm = _dev_utils::init_test().await;
+        let ctx = _dev_utils::ctx_test(1000);
+        let fx_call = json!({"jsonrpc": "2.0", "id": 1, "method": "unknown_method"});
+
+        // -- Session expired.
+        let addr = serve_ws(
+            mm.clone(),
+            ctx.clone(),
+            now_utc() + time::Duration::milliseconds(200),
+        );
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws")).await?;
+        assert_eq!(recv_close_reason(&mut socket).await?, "session expired");
+
+        // -- Membership lost.
+        WorkspaceBmc::add_member(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID, 1001).await?;
+        let addr = serve_ws(mm.clone(), _dev_utils::ctx_test(1001), fx_session_exp());
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws")).await?;
+        socket
+            .send(tungstenite::Message::Text(fx_call.to_string()))
+            .await?;
+        assert_eq!(recv_json(&mut socket).await?["id"], 1);
+
+        WorkspaceBmc::remove_member(&ctx, &mm, _dev_utils::DEMO_WORKSPACE_ID, 1001).await?;
+        socket
+            .send(tungstenite::Message::Text(fx_call.to_string()))
+            .await?;
+        assert_eq!(
+            recv_close_reason(&mut socket).await?,
+            "workspace membership lost"
+        );
+
+        Ok(())
+    }
+}